@@ -3,24 +3,187 @@
 #![allow(dead_code)]
 #![deny(missing_docs)]
 
-use std::fs::read_to_string;
+use std::{env, fs};
 
 use utility::generation::{
-    tile::{RawFile, TileSet},
+    format::Format,
+    inheritance::Inherited,
+    migration::{Migration, CURRENT_VERSION},
+    preview::Preview,
+    raster::{Fill, Raster},
+    solvability::{Solvability, DEFAULT_BUDGET},
+    sprite::Sprites,
+    text::Text,
+    tile::{
+        dynamic::{DynTileSet, WithPrecision},
+        RawFile, TileSet,
+    },
+    tiled::Tileset,
+    validation::Report,
     wfc::Generator,
 };
 
 mod collections;
 mod utility;
 
+/// Path to the tile file that is loaded on startup
+const TILE_FILE: &str = "data/tiles/test.ron";
+
 fn main() {
-    let file = read_to_string("data/tiles/test.ron").unwrap();
-    let raw: RawFile = ron::from_str(file.as_str()).unwrap();
-    let mut set = TileSet::<3>::new(raw.id);
-    set.add_all_raws(&raw.tiles);
+    let mut args = env::args().skip(1);
+
+    let first = args.next();
+
+    if let Some("migrate") = first.as_deref() {
+        let paths: Vec<String> = args.collect();
+        let paths = if paths.is_empty() {
+            vec![TILE_FILE.to_string()]
+        } else {
+            paths
+        };
+
+        for path in paths {
+            match Migration::rewrite(&path) {
+                Ok(CURRENT_VERSION) => println!("{path}: already up to date"),
+                Ok(from) => println!("{path}: upgraded from version {from} to {CURRENT_VERSION}"),
+                Err(error) => eprintln!("error: {path}: {error}"),
+            }
+        }
+
+        return;
+    }
+    if let Some("convert") = first.as_deref() {
+        let (Some(input), Some(output)) = (args.next(), args.next()) else {
+            eprintln!("error: usage: convert <input> <output>");
+            return;
+        };
+        let format = Format::from_extension(&output).unwrap_or_default();
+        let result = Migration::read(&input)
+            .map_err(|e| e.to_string())
+            .and_then(|(m, _)| format.encode(m.file()).map_err(|e| e.to_string()))
+            .and_then(|bytes| fs::write(&output, bytes).map_err(|e| e.to_string()));
+
+        match result {
+            Ok(()) => println!("{input}: converted to {output}"),
+            Err(error) => eprintln!("error: {input}: {error}"),
+        }
+
+        return;
+    }
+
+    if let Some("import") = first.as_deref() {
+        let (Some(input), Some(output)) = (args.next(), args.next()) else {
+            eprintln!("error: usage: import <tileset> <output> [precision] [wang set]");
+            return;
+        };
+        let Ok(precision) = args.next().as_deref().unwrap_or("3").parse() else {
+            eprintln!("error: precision must be a number");
+            return;
+        };
+        let wang_set = args.next().unwrap_or_default();
+        let format = Format::from_extension(&output).unwrap_or_default();
+        let result = Tileset::load(&input)
+            .and_then(|t| t.to_raw_file(&wang_set, 0, precision))
+            .map_err(|e| e.to_string())
+            .and_then(|file| format.encode(&file).map_err(|e| e.to_string()))
+            .and_then(|bytes| fs::write(&output, bytes).map_err(|e| e.to_string()));
+
+        match result {
+            Ok(()) => println!("{input}: imported to {output}"),
+            Err(error) => eprintln!("error: {input}: {error}"),
+        }
+
+        return;
+    }
+
+    let inherited = match Inherited::load(TILE_FILE) {
+        Ok(inherited) => inherited,
+        Err(error) => {
+            eprintln!("error: {error}");
+            return;
+        }
+    };
+
+    for (path, version) in inherited.outdated() {
+        eprintln!(
+            "warning: {}: version {version} is outdated, run `migrate` to upgrade it to version {CURRENT_VERSION}",
+            path.display()
+        );
+    }
+
+    let raw = inherited.into_file();
+    let preview = match first.as_deref() {
+        Some("preview") => Some(args.next().unwrap_or_else(|| "preview.png".to_string())),
+        _ => None,
+    };
+
+    if let Err(error) = DynTileSet::from_file(&raw).dispatch(Run { raw: &raw, preview }) {
+        eprintln!("error: {error}");
+    }
+}
+
+/// Validates a tile file, then generates a map from it and displays it
+struct Run<'a> {
+    /// The tile file, which the tile set was created from
+    raw: &'a RawFile,
+    /// Path that a preview image of the map is saved to, instead of printing the map
+    preview: Option<String>,
+}
+
+impl WithPrecision for Run<'_> {
+    type Output = ();
+
+    fn run<const P: usize>(self, set: TileSet<P>) {
+        let raw = self.raw;
+        let report = Report::new::<P>(raw);
+        report.diagnostics().iter().for_each(|d| eprintln!("{d}"));
+
+        if report.has_errors() {
+            return;
+        }
+
+        let mut gen = Generator::new(3, 3, set.tiles());
+
+        match Solvability::check(&gen, false, DEFAULT_BUDGET) {
+            Solvability::Unsolvable(example) => {
+                eprintln!("error: {example}");
+                return;
+            }
+            Solvability::Invalid(error) => {
+                eprintln!("error: {error}");
+                return;
+            }
+            _ => {}
+        }
+
+        let map = match gen.run(false) {
+            Ok(map) => map,
+            Err(error) => {
+                eprintln!("error: {error}");
+                return;
+            }
+        };
+
+        if let Some(output) = self.preview {
+            let result = Sprites::new(&raw.tiles)
+                .map_err(|e| e.to_string())
+                .and_then(|sprites| {
+                    Preview::new(32)
+                        .with_overlay(true)
+                        .save(&map, &sprites, &output)
+                        .map_err(|e| e.to_string())
+                });
+
+            match result {
+                Ok(()) => println!("preview saved to {output}"),
+                Err(error) => eprintln!("error: {error}"),
+            }
+
+            return;
+        }
 
-    let mut gen = Generator::new(3, 3, set.tiles());
-    let map = gen.run(false).unwrap();
+        let raster = Raster::from_raws(Fill::default(), &raw.tiles);
 
-    println!("{:?}", map);
+        print!("{}", Text::new(set.tiles(), raster).render(&map));
+    }
 }
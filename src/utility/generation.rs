@@ -1,5 +1,33 @@
 use std::array::IntoIter;
 
+use serde::{Deserialize, Serialize};
+
+use crate::collections::grid::Idx;
+
+pub mod adjacency;
+pub mod constraint;
+pub mod explanation;
+#[cfg(test)]
+pub(crate) mod fixtures;
+pub mod format;
+pub mod frequency;
+pub mod graph;
+pub mod hierarchy;
+pub mod inheritance;
+pub mod metadata;
+pub mod migration;
+pub mod navigation;
+pub mod prefab;
+pub mod preview;
+pub mod raster;
+pub mod solvability;
+pub mod sprite;
+pub mod text;
+pub mod tile;
+pub mod tiled;
+pub mod validation;
+pub mod wfc;
+
 /// Represents one of four possible sides of a generator tile
 #[repr(usize)]
 #[derive(Clone, Copy, Debug, Default, Hash, PartialEq, Eq, PartialOrd, Ord)]
@@ -66,6 +94,30 @@ impl Side {
             Self::relative_x(start.0, end.0)
         }
     }
+    /// Returns the index next to the provided index on this side, within a grid of the given size.
+    ///
+    /// If `wrap` is `true`, indexes on the edge of the grid are considered next to the indexes on the
+    /// opposite edge.
+    ///
+    /// # Examples
+    /// ```rust
+    /// assert_eq!(Side::Right.neighbor((0, 0), (2, 2), false), Some((1, 0)));
+    /// assert_eq!(Side::Top.neighbor((0, 0), (2, 2), false), None);
+    /// assert_eq!(Side::Top.neighbor((0, 0), (2, 2), true), Some((0, 1)));
+    /// ```
+    pub const fn neighbor(self, (x, y): Idx, (w, h): Idx, wrap: bool) -> Option<Idx> {
+        match self {
+            Self::Top if y > 0 => Some((x, y - 1)),
+            Self::Top if wrap && h > 0 => Some((x, h - 1)),
+            Self::Left if x > 0 => Some((x - 1, y)),
+            Self::Left if wrap && w > 0 => Some((w - 1, y)),
+            Self::Right if x + 1 < w => Some((x + 1, y)),
+            Self::Right if wrap => Some((0, y)),
+            Self::Bottom if y + 1 < h => Some((x, y + 1)),
+            Self::Bottom if wrap => Some((x, 0)),
+            _ => None,
+        }
+    }
 }
 
 impl From<Side> for usize {
@@ -88,7 +140,9 @@ impl IntoIterator for Side {
 
 /// Represents one of four possible rotations of a generator tile
 #[repr(usize)]
-#[derive(Clone, Copy, Debug, Default, Hash, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(
+    Clone, Copy, Debug, Default, Hash, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize,
+)]
 pub enum Rotation {
     #[default]
     D0 = 0,
@@ -0,0 +1,91 @@
+use crate::collections::grid::{vec::VecGrid, Grid};
+
+use super::{tile::Tile, Side};
+
+/// Tiles that may be placed next to each other, indexed by tile and then by side
+#[derive(Clone, Debug, Default, Hash, PartialEq, Eq)]
+pub struct Adjacency(Vec<[Vec<usize>; 4]>);
+
+impl Adjacency {
+    /// Creates new adjacency rules where tiles may be placed next to each other if their nodes fit
+    pub fn from_nodes<const P: usize>(tiles: &[Tile<P>]) -> Self {
+        let rules = tiles
+            .iter()
+            .map(|tile| {
+                [Side::Top, Side::Left, Side::Right, Side::Bottom].map(|side| {
+                    (0..tiles.len())
+                        .filter(|other| tile.fits(&tiles[*other], side))
+                        .collect()
+                })
+            })
+            .collect();
+
+        Self(rules)
+    }
+    /// Creates new adjacency rules for the given number of tiles, where tiles may only be placed next
+    /// to each other if they were next to each other within the provided example map.
+    ///
+    /// The example stores the index of the tile within each cell. Empty cells and indexes of tiles
+    /// outside of the rules are ignored. If `wrap` is `true`, cells on opposite edges of the example
+    /// are treated as neighbours.
+    pub fn from_example(tiles: usize, example: &VecGrid<usize>, wrap: bool) -> Self {
+        let mut rules = Self(vec![Default::default(); tiles]);
+        let size = example.size();
+
+        for index in example.indexes() {
+            let Some(&tile) = example.get(index).filter(|t| **t < tiles) else {
+                continue;
+            };
+
+            for side in Side::Top {
+                let Some(neighbor) = side.neighbor(index, size, wrap) else {
+                    continue;
+                };
+
+                if let Some(&other) = example.get(neighbor).filter(|t| **t < tiles) {
+                    rules.allow(tile, side, other);
+                }
+            }
+        }
+
+        rules
+    }
+
+    /// Returns the total number of tiles with rules
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+    /// Returns `true` if there are no tiles with rules
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+    /// Returns the indexes of the tiles that may be placed on the given side of the provided tile
+    pub fn get(&self, tile: usize, side: Side) -> &[usize] {
+        &self.0[tile][usize::from(side)]
+    }
+
+    /// Allows the `other` tile to be placed on the given side of the provided tile, and the provided
+    /// tile to be placed on the opposite side of the `other` tile
+    pub fn allow(&mut self, tile: usize, side: Side, other: usize) {
+        for (tile, side, other) in [(tile, side, other), (other, side.opposite(), tile)] {
+            let allowed = &mut self.0[tile][usize::from(side)];
+
+            if let Err(position) = allowed.binary_search(&other) {
+                allowed.insert(position, other);
+            }
+        }
+    }
+    /// Only allows the `other` tile on the given side of the provided tile, and only the provided
+    /// tile on the opposite side of the `other` tile
+    pub fn link(&mut self, tile: usize, side: Side, other: usize) {
+        for (tile, side) in [(tile, side), (other, side.opposite())] {
+            let removed = std::mem::take(&mut self.0[tile][usize::from(side)]);
+
+            for neighbor in removed {
+                self.0[neighbor][usize::from(side.opposite())].retain(|t| *t != tile);
+            }
+        }
+
+        self.allow(tile, side, other);
+    }
+}
@@ -0,0 +1,1342 @@
+use std::{cell::RefCell, fmt::Debug};
+
+use crate::collections::grid::{vec::VecGrid, Grid, Idx};
+
+use super::{
+    metadata::Metadata,
+    tile::{Node, Tile},
+    wfc::{Error, Wave},
+    Side,
+};
+
+/// Part of a larger map that is filled on its own, such as while checking whether the map can be
+/// filled
+#[derive(Clone, Copy, Debug, Default, Hash, PartialEq, Eq)]
+pub struct Window {
+    /// Position of the window's first cell within the map
+    pub offset: Idx,
+    /// Size of the window
+    pub size: Idx,
+    /// Size of the whole map
+    pub map: Idx,
+    /// Whether cells on opposite edges of the whole map are neighbours
+    pub wrap: bool,
+}
+
+impl Window {
+    /// Returns the index within the window of the provided index within the map, if the window
+    /// contains it
+    pub const fn local(&self, (x, y): Idx) -> Option<Idx> {
+        let (ox, oy) = self.offset;
+
+        if x >= ox && y >= oy && x - ox < self.size.0 && y - oy < self.size.1 {
+            Some((x - ox, y - oy))
+        } else {
+            None
+        }
+    }
+    /// Returns the index within the map of the provided index within the window
+    pub const fn global(&self, (x, y): Idx) -> Idx {
+        (x + self.offset.0, y + self.offset.1)
+    }
+    /// Returns `true` if the window's edge on the given side lies along the map's own edge
+    pub const fn touches(&self, side: Side) -> bool {
+        let ((x, y), (w, h), (mw, mh)) = (self.offset, self.size, self.map);
+
+        !self.wrap
+            && match side {
+                Side::Top => y == 0,
+                Side::Left => x == 0,
+                Side::Right => x + w == mw,
+                Side::Bottom => y + h == mh,
+            }
+    }
+}
+
+/// Additional rule that is enforced by a generator after each propagation
+pub trait Constraint<const P: usize>: Debug {
+    /// Removes every tile from a newly prepared wave that can never satisfy the constraint, returning
+    /// the index of each cell that was changed
+    fn initialize(&self, _: &[Tile<P>], _: &mut Wave, _: bool) -> Result<Vec<Idx>, Error> {
+        Ok(vec![])
+    }
+    /// Removes every tile from the wave that would break the constraint, returning the index of each
+    /// cell that was changed.
+    ///
+    /// `changed` holds every cell that has changed since the constraint was last enforced.
+    fn enforce(
+        &self,
+        tiles: &[Tile<P>],
+        wave: &mut Wave,
+        changed: &[Idx],
+        wrap: bool,
+    ) -> Result<Vec<Idx>, Error>;
+    /// Returns the constraint to enforce when filling the provided window of the map on its own, or
+    /// `None` if the constraint can not be enforced within the window.
+    ///
+    /// Every map that satisfies this constraint must also satisfy the returned constraint within
+    /// the window, so that a window that can not be filled proves that the map can not be filled.
+    fn window(&self, _: &Window) -> Option<Box<dyn Constraint<P>>> {
+        None
+    }
+}
+
+/// Bans every tile at the provided index that does not match the given predicate, returning `true`
+/// if any tile was banned
+fn retain(
+    wave: &mut Wave,
+    index: Idx,
+    mut predicate: impl FnMut(usize) -> bool,
+) -> Result<bool, Error> {
+    let banned: Vec<usize> = wave.possible(index).filter(|t| !predicate(*t)).collect();
+
+    for tile in &banned {
+        wave.ban(index, *tile);
+    }
+    if wave.count(index) == 0 {
+        return Err(Error::Contradiction(index));
+    }
+
+    Ok(!banned.is_empty())
+}
+
+/// Restricts cells to a chosen set of tiles
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub struct Mask(VecGrid<Vec<usize>>);
+
+impl Mask {
+    /// Creates a new mask, where each cell may only contain the tiles at the stored indexes.
+    ///
+    /// Empty cells and cells outside of the grid are not restricted.
+    pub const fn new(allowed: VecGrid<Vec<usize>>) -> Self {
+        Self(allowed)
+    }
+}
+
+impl<const P: usize> Constraint<P> for Mask {
+    fn initialize(&self, _: &[Tile<P>], wave: &mut Wave, _: bool) -> Result<Vec<Idx>, Error> {
+        let mut changed = vec![];
+
+        for index in self.0.indexes() {
+            let Some(allowed) = self.0.get(index) else {
+                continue;
+            };
+
+            if wave.contains(index) && retain(wave, index, |t| allowed.contains(&t))? {
+                changed.push(index);
+            }
+        }
+
+        Ok(changed)
+    }
+    fn enforce(&self, _: &[Tile<P>], _: &mut Wave, _: &[Idx], _: bool) -> Result<Vec<Idx>, Error> {
+        Ok(vec![])
+    }
+    fn window(&self, window: &Window) -> Option<Box<dyn Constraint<P>>> {
+        let mut allowed = VecGrid::new(window.size.0, window.size.1);
+
+        for index in allowed.indexes() {
+            if let Some(tiles) = self.0.get(window.global(index)) {
+                allowed.insert(index, tiles.clone());
+            }
+        }
+
+        Some(Box::new(Self(allowed)))
+    }
+}
+
+/// Decides which sides of a tile may be travelled through
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub enum Traversal {
+    /// Sides that contain the given node may be travelled through
+    Node(Node),
+    /// Every side of the tiles at the given indexes may be travelled through
+    Tiles(Vec<usize>),
+}
+
+impl Traversal {
+    /// Returns `true` if the given side of the provided tile may be travelled through
+    pub fn is_open<const P: usize>(&self, tiles: &[Tile<P>], tile: usize, side: Side) -> bool {
+        match self {
+            Self::Node(node) => tiles[tile].nodes(side).contains(node),
+            Self::Tiles(open) => open.contains(&tile),
+        }
+    }
+}
+
+/// Requires a route to exist between two cells.
+///
+/// Each tile is assumed to connect all of its open sides together. Whenever every remaining route
+/// passes through a single side of a cell, tiles that would close that side or lead nowhere from it
+/// are removed. Routes that are closed off by several cells at once are only found once they are
+/// closed, which fails with `Error::NoPath`, so the generator undoes its choices until a route is
+/// open again. Generated maps therefore always have a route, and generation only fails with
+/// `Error::NoPath` if no map can have one.
+///
+/// Routes are always found across a grid, so this constraint does not support graph generators.
+/// Routes may also leave any window of the map, so the constraint is not enforced within windows.
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub struct Path {
+    /// Index of the route's first cell
+    start: Idx,
+    /// Index of the route's last cell
+    end: Idx,
+    /// Decides which sides of a tile may be travelled through
+    traversal: Traversal,
+}
+
+impl Path {
+    /// Creates a new path constraint between the given cells
+    pub const fn new(start: Idx, end: Idx, traversal: Traversal) -> Self {
+        Self {
+            start,
+            end,
+            traversal,
+        }
+    }
+
+    /// Returns the index of the route's first cell
+    pub const fn start(&self) -> Idx {
+        self.start
+    }
+    /// Returns the index of the route's last cell
+    pub const fn end(&self) -> Idx {
+        self.end
+    }
+}
+
+/// Point that a route may pass through while searching for a path
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+enum Vertex {
+    /// Where every route begins, joined to each open side of the first cell
+    Start,
+    /// Where every route ends, joined to each open side of the last cell
+    End,
+    /// The given side of the cell at the given index
+    Port(Idx, Side),
+}
+
+/// Depth-first search tree over the sides of every cell that can be reached from a route's start.
+///
+/// A route may pass through a cell between two of its sides if a single possible tile is open on
+/// both, and may cross between neighbouring cells if both are open towards each other.
+struct Search {
+    /// Size of the searched wave
+    size: Idx,
+    /// Index of the route's first cell
+    start: Idx,
+    /// Index of the route's last cell
+    end: Idx,
+    /// Whether cells on opposite edges of the wave are neighbours
+    wrap: bool,
+    /// Index of a cell that routes may not pass through
+    excluded: Option<Idx>,
+    /// Whether each pair of sides of each cell is open on a single possible tile, where a side
+    /// paired with itself is open on any possible tile
+    joined: Vec<[[bool; 4]; 4]>,
+    /// Time at which each vertex was first visited
+    discovered: Vec<usize>,
+    /// Latest discovery time of each vertex's descendants
+    finished: Vec<usize>,
+    /// Earliest discovery time reachable from each vertex's descendants without using its parent
+    /// edge
+    low: Vec<usize>,
+    /// Parent of each vertex
+    parents: Vec<Option<Vertex>>,
+}
+
+impl Search {
+    /// Searches the provided wave for routes between the given cells that avoid the excluded cell
+    fn new<const P: usize>(
+        traversal: &Traversal,
+        tiles: &[Tile<P>],
+        wave: &Wave,
+        (start, end): (Idx, Idx),
+        excluded: Option<Idx>,
+        wrap: bool,
+    ) -> Self {
+        let (width, height) = wave.size();
+        let joined = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .map(|index| {
+                let mut joined = [[false; 4]; 4];
+
+                for tile in wave.possible(index) {
+                    let open = [Side::Top, Side::Left, Side::Right, Side::Bottom]
+                        .map(|side| traversal.is_open(tiles, tile, side));
+
+                    for (a, row) in joined.iter_mut().enumerate() {
+                        for (b, joined) in row.iter_mut().enumerate() {
+                            *joined |= open[a] && open[b];
+                        }
+                    }
+                }
+
+                joined
+            })
+            .collect();
+        let vertices = width * height * 4 + 2;
+        let mut search = Self {
+            size: (width, height),
+            start,
+            end,
+            wrap,
+            excluded,
+            joined,
+            discovered: vec![usize::MAX; vertices],
+            finished: vec![usize::MAX; vertices],
+            low: vec![usize::MAX; vertices],
+            parents: vec![None; vertices],
+        };
+
+        search.run();
+        search
+    }
+
+    /// Returns the position of the provided vertex within the search's lists
+    const fn id(&self, vertex: Vertex) -> usize {
+        let cells = self.size.0 * self.size.1;
+
+        match vertex {
+            Vertex::Start => cells * 4,
+            Vertex::End => cells * 4 + 1,
+            Vertex::Port((x, y), side) => (y * self.size.0 + x) * 4 + side as usize,
+        }
+    }
+    /// Returns `true` if any possible tile of the cell at the provided index is open on the given
+    /// side
+    fn is_open(&self, (x, y): Idx, side: Side) -> bool {
+        let side = usize::from(side);
+
+        self.joined[y * self.size.0 + x][side][side]
+    }
+    /// Returns the vertices joined to the provided vertex
+    fn neighbors(&self, vertex: Vertex) -> Vec<Vertex> {
+        let ports = |index: Idx| {
+            Side::Top
+                .into_iter()
+                .filter(move |side| self.is_open(index, *side))
+                .map(move |side| Vertex::Port(index, side))
+        };
+
+        let neighbors: Vec<Vertex> = match vertex {
+            Vertex::Start => ports(self.start).collect(),
+            Vertex::End => ports(self.end).collect(),
+            Vertex::Port(index, side) => {
+                let (x, y) = index;
+                let joined = &self.joined[y * self.size.0 + x][usize::from(side)];
+                let mut neighbors: Vec<Vertex> = Side::Top
+                    .into_iter()
+                    .filter(|other| *other != side && joined[usize::from(*other)])
+                    .map(|other| Vertex::Port(index, other))
+                    .collect();
+
+                if let Some(next) = side.neighbor(index, self.size, self.wrap) {
+                    if self.is_open(next, side.opposite()) {
+                        neighbors.push(Vertex::Port(next, side.opposite()));
+                    }
+                }
+                if index == self.start {
+                    neighbors.push(Vertex::Start);
+                }
+                if index == self.end {
+                    neighbors.push(Vertex::End);
+                }
+
+                neighbors
+            }
+        };
+
+        neighbors
+            .into_iter()
+            .filter(|v| !matches!(v, Vertex::Port(i, _) if Some(*i) == self.excluded))
+            .collect()
+    }
+    /// Visits every vertex that can be reached from the start
+    fn run(&mut self) {
+        let root = self.id(Vertex::Start);
+        let mut time = 0;
+
+        self.discovered[root] = time;
+        self.low[root] = time;
+
+        // Each frame stores a vertex, the edges it has yet to explore, and whether the edge used to
+        // enter it has been skipped.
+        let mut stack = vec![(Vertex::Start, self.neighbors(Vertex::Start), false)];
+
+        while let Some((vertex, edges, skipped)) = stack.last_mut() {
+            let vertex = *vertex;
+            let parent = self.parents[self.id(vertex)];
+
+            if let Some(next) = edges.pop() {
+                // Only one edge back to the parent is skipped, so that parallel edges still count.
+                if !*skipped && Some(next) == parent {
+                    *skipped = true;
+                    continue;
+                }
+
+                let (current, next_id) = (self.id(vertex), self.id(next));
+
+                if self.discovered[next_id] == usize::MAX {
+                    time += 1;
+                    self.discovered[next_id] = time;
+                    self.low[next_id] = time;
+                    self.parents[next_id] = Some(vertex);
+
+                    stack.push((next, self.neighbors(next), false));
+                } else {
+                    self.low[current] = self.low[current].min(self.discovered[next_id]);
+                }
+
+                continue;
+            }
+
+            let current = self.id(vertex);
+
+            stack.pop();
+            self.finished[current] = time;
+
+            if let Some(parent) = parent {
+                let parent = self.id(parent);
+
+                self.low[parent] = self.low[parent].min(self.low[current]);
+            }
+        }
+    }
+
+    /// Returns the time at which the provided vertex was first visited
+    fn discovered(&self, vertex: Vertex) -> usize {
+        self.discovered[self.id(vertex)]
+    }
+    /// Returns the lowest discovery time reachable from the provided vertex
+    fn low(&self, vertex: Vertex) -> usize {
+        self.low[self.id(vertex)]
+    }
+    /// Returns `true` if the provided vertex was reached
+    fn reached(&self, vertex: Vertex) -> bool {
+        self.discovered(vertex) != usize::MAX
+    }
+    /// Returns `true` if the given vertex is the provided ancestor or one of its descendants
+    fn descends(&self, vertex: Vertex, ancestor: Vertex) -> bool {
+        let ancestor = self.id(ancestor);
+
+        (self.discovered[ancestor]..=self.finished[ancestor]).contains(&self.discovered(vertex))
+    }
+    /// Returns every vertex along the search tree's route from the start to the end
+    fn route(&self) -> Vec<Vertex> {
+        let mut route = vec![Vertex::End];
+
+        while let Some(parent) = self.parents[self.id(route[route.len() - 1])] {
+            route.push(parent);
+        }
+
+        route.reverse();
+        route
+    }
+    /// Returns the sides of the cell at the provided index, one of which must be open alongside the
+    /// given side that every route passes through, where `next` follows the side along the search
+    /// tree's route. Returns `None` for the route's ends, which are joined to the search directly.
+    fn onward(&self, index: Idx, side: Side, next: Vertex) -> Option<Vec<Side>> {
+        if index == self.start || index == self.end {
+            return None;
+        }
+
+        // Routes reach the end through the descendants of `next`, so a route crossing the side
+        // crosses another side of the cell from the other part of the search.
+        let beyond = |side: Side| {
+            side.neighbor(index, self.size, self.wrap).map(|n| {
+                let port = Vertex::Port(n, side.opposite());
+
+                self.reached(port) && self.descends(port, next)
+            })
+        };
+        let across = beyond(side).unwrap_or(false);
+        let sides = Side::Top.into_iter().filter(|other| {
+            let port = other.neighbor(index, self.size, self.wrap);
+            let port = port.map(|n| Vertex::Port(n, other.opposite()));
+
+            *other != side
+                && port.is_some_and(|p| self.reached(p))
+                && beyond(*other) == Some(!across)
+        });
+
+        Some(sides.collect())
+    }
+    /// Returns the sides of the cell at the provided index whose neighbour was reached, through
+    /// which a route from the start may enter the cell
+    fn entries(&self, index: Idx) -> Vec<Side> {
+        Side::Top
+            .into_iter()
+            .filter(|side| {
+                side.neighbor(index, self.size, self.wrap)
+                    .is_some_and(|n| self.reached(Vertex::Port(n, side.opposite())))
+            })
+            .collect()
+    }
+}
+
+impl<const P: usize> Constraint<P> for Path {
+    fn initialize(&self, _: &[Tile<P>], wave: &mut Wave, _: bool) -> Result<Vec<Idx>, Error> {
+        match [self.start, self.end]
+            .into_iter()
+            .find(|i| !wave.contains(*i))
+        {
+            Some(index) => Err(Error::OutOfBounds(index)),
+            None => Ok(vec![]),
+        }
+    }
+    fn enforce(
+        &self,
+        tiles: &[Tile<P>],
+        wave: &mut Wave,
+        _: &[Idx],
+        wrap: bool,
+    ) -> Result<Vec<Idx>, Error> {
+        if self.start == self.end {
+            return Ok(vec![]);
+        }
+
+        let (start, end, traversal) = (self.start, self.end, &self.traversal);
+        let forward = Search::new(traversal, tiles, wave, (start, end), None, wrap);
+
+        if !forward.reached(Vertex::End) {
+            return Err(Error::NoPath(start, end));
+        }
+
+        // Each end is searched from the other without passing through it, so that only sides
+        // leading away from the end are counted.
+        let to_start = Search::new(traversal, tiles, wave, (end, start), Some(start), wrap);
+        let to_end = Search::new(traversal, tiles, wave, (start, end), Some(end), wrap);
+        let open =
+            |t: usize, sides: &[Side]| sides.iter().all(|s| self.traversal.is_open(tiles, t, *s));
+        // Cells left without a tile along the route also close it off.
+        let closed = |_: Error| Error::NoPath(start, end);
+        let mut changed = vec![];
+
+        // Every route passes through each side along the search tree's route that separates the
+        // start from the end, so its cell must hold a tile that is open on it and leads onwards.
+        for pair in forward.route().windows(2) {
+            let (Vertex::Port(index, side), next) = (pair[0], pair[1]) else {
+                continue;
+            };
+
+            if forward.low(next) < forward.discovered(pair[0]) {
+                continue;
+            }
+
+            let onward = forward.onward(index, side, next);
+            let leads = |t| {
+                onward
+                    .as_ref()
+                    .is_none_or(|o| o.iter().any(|s| open(t, &[*s])))
+            };
+
+            if retain(wave, index, |t| open(t, &[side]) && leads(t)).map_err(closed)?
+                && !changed.contains(&index)
+            {
+                changed.push(index);
+            }
+        }
+
+        // Both ends must be open on a side that still leads to the other end.
+        for (index, exits) in [(start, to_start.entries(start)), (end, to_end.entries(end))] {
+            if retain(wave, index, |t| exits.iter().any(|s| open(t, &[*s]))).map_err(closed)? {
+                changed.push(index);
+            }
+        }
+
+        Ok(changed)
+    }
+}
+
+/// Returns every index within `reach` cells of the provided index horizontally and vertically
+fn neighborhood((x, y): Idx, reach: usize, (w, h): Idx, wrap: bool) -> Vec<Idx> {
+    let span = |center: usize, length: usize| -> Vec<usize> {
+        if wrap {
+            (0..=reach * 2)
+                .map(|offset| (center + length * reach + offset - reach) % length)
+                .collect()
+        } else {
+            (center.saturating_sub(reach)..=(center + reach).min(length - 1)).collect()
+        }
+    };
+    let columns = span(x, w);
+
+    span(y, h)
+        .into_iter()
+        .flat_map(|ny| columns.iter().map(move |nx| (*nx, ny)))
+        .collect()
+}
+
+/// Method used to measure the distance between two cells
+#[derive(Clone, Copy, Debug, Default, Hash, PartialEq, Eq)]
+pub enum Metric {
+    /// Sum of the horizontal and vertical distances
+    Manhattan,
+    /// Largest of the horizontal and vertical distances
+    #[default]
+    Chebyshev,
+    /// Straight line distance, rounded up
+    Euclidean,
+}
+
+impl Metric {
+    /// Returns the distance between the provided indexes within a grid of the given size.
+    ///
+    /// If `wrap` is `true`, distances may cross the edges of the grid.
+    pub fn distance(self, start: Idx, end: Idx, (w, h): Idx, wrap: bool) -> usize {
+        let mut dx = start.0.abs_diff(end.0);
+        let mut dy = start.1.abs_diff(end.1);
+
+        if wrap {
+            dx = dx.min(w - dx);
+            dy = dy.min(h - dy);
+        }
+
+        match self {
+            Self::Manhattan => dx + dy,
+            Self::Chebyshev => dx.max(dy),
+            Self::Euclidean => {
+                let squared = dx * dx + dy * dy;
+                let mut root = (squared as f64).sqrt() as usize;
+
+                while root * root < squared {
+                    root += 1;
+                }
+
+                root
+            }
+        }
+    }
+}
+
+/// Requires every tile with a given tag to be placed at least a minimum distance apart from each
+/// other, in any rotation.
+///
+/// Distances are measured across a grid, so graph generators fail with `Error::GridOnly`.
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub struct Spacing {
+    /// Tags of the raw tiles
+    metadata: Metadata,
+    /// Tag of the tiles that must be spaced apart
+    tag: String,
+    /// Minimum distance between any two of the tiles
+    distance: usize,
+    /// Method used to measure distance
+    metric: Metric,
+}
+
+impl Spacing {
+    /// Creates a new spacing constraint, requiring tiles with the given tag within the provided
+    /// metadata to be at least `distance` cells apart when measured using the provided metric.
+    ///
+    /// A constraint where no two tiles may be within a radius of `r` cells has a distance of `r + 1`.
+    pub fn new(metadata: Metadata, tag: &str, distance: usize, metric: Metric) -> Self {
+        Self {
+            metadata,
+            tag: tag.to_string(),
+            distance,
+            metric,
+        }
+    }
+
+    /// Returns the tag of the tiles that must be spaced apart
+    pub fn tag(&self) -> &str {
+        &self.tag
+    }
+    /// Returns the minimum distance between any two of the tiles
+    pub const fn distance(&self) -> usize {
+        self.distance
+    }
+}
+
+impl<const P: usize> Constraint<P> for Spacing {
+    fn initialize(&self, _: &[Tile<P>], wave: &mut Wave, _: bool) -> Result<Vec<Idx>, Error> {
+        if wave.is_grid() {
+            Ok(vec![])
+        } else {
+            Err(Error::GridOnly)
+        }
+    }
+    fn enforce(
+        &self,
+        tiles: &[Tile<P>],
+        wave: &mut Wave,
+        changed: &[Idx],
+        wrap: bool,
+    ) -> Result<Vec<Idx>, Error> {
+        let size = wave.size();
+        let reach = self.distance.saturating_sub(1);
+        let spaced: Vec<bool> = tiles
+            .iter()
+            .map(|t| self.metadata.has_tag(t, &self.tag))
+            .collect();
+        let mut banned = vec![];
+
+        // Only cells that can no longer hold any other tile affect their neighbourhood, and each
+        // does so once, as soon as it changes.
+        for &cell in changed {
+            if wave.possible(cell).any(|t| !spaced[t]) {
+                continue;
+            }
+
+            let mut nearby = neighborhood(cell, reach, size, wrap);
+
+            nearby.sort_unstable();
+            nearby.dedup();
+
+            for index in nearby {
+                if index == cell || self.metric.distance(cell, index, size, wrap) >= self.distance {
+                    continue;
+                }
+                if retain(wave, index, |t| !spaced[t])? {
+                    banned.push(index);
+                }
+            }
+        }
+
+        Ok(banned)
+    }
+    fn window(&self, _: &Window) -> Option<Box<dyn Constraint<P>>> {
+        // Cells within a window are never closer than they are within the map.
+        Some(Box::new(self.clone()))
+    }
+}
+
+/// Decides which tiles may be placed along one edge of the map
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub enum Edge {
+    /// Only the tiles at the given indexes may be placed
+    Tiles(Vec<usize>),
+    /// Only tiles whose nodes facing out of the map are all one of the given nodes may be placed
+    Nodes(Vec<Node>),
+}
+
+impl Edge {
+    /// Returns `true` if the provided tile may be placed along the edge on the given side
+    pub fn allows<const P: usize>(&self, tiles: &[Tile<P>], tile: usize, side: Side) -> bool {
+        match self {
+            Self::Tiles(allowed) => allowed.contains(&tile),
+            Self::Nodes(allowed) => tiles[tile].nodes(side).iter().all(|n| allowed.contains(n)),
+        }
+    }
+}
+
+/// Restricts the tiles placed along each outer edge of the map.
+///
+/// Wrapping maps have no outer edges, so this constraint does nothing when wrapping. Within a window
+/// of the map, only the edges that lie along the map's own edges are restricted. Edges are always
+/// found around a grid, so graph generators fail with `Error::GridOnly`.
+#[derive(Clone, Debug, Default, Hash, PartialEq, Eq)]
+pub struct Border([Option<Edge>; 4]);
+
+impl Border {
+    /// Creates a new border constraint that does not restrict any edge
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the restriction on the edge of the map on the given side, if any
+    pub fn get(&self, side: Side) -> Option<&Edge> {
+        self.0[usize::from(side)].as_ref()
+    }
+    /// Sets the restriction on the edge of the map on the given side
+    pub fn set(&mut self, side: Side, edge: Edge) {
+        self.0[usize::from(side)] = Some(edge);
+    }
+}
+
+impl<const P: usize> Constraint<P> for Border {
+    fn initialize(
+        &self,
+        tiles: &[Tile<P>],
+        wave: &mut Wave,
+        wrap: bool,
+    ) -> Result<Vec<Idx>, Error> {
+        let (width, height) = wave.size();
+        let mut changed = vec![];
+
+        if !wave.is_grid() {
+            return Err(Error::GridOnly);
+        }
+        if wrap || width == 0 || height == 0 {
+            return Ok(changed);
+        }
+
+        for side in Side::Top {
+            let Some(edge) = self.get(side) else {
+                continue;
+            };
+            let cells: Vec<Idx> = match side {
+                Side::Top => (0..width).map(|x| (x, 0)).collect(),
+                Side::Left => (0..height).map(|y| (0, y)).collect(),
+                Side::Right => (0..height).map(|y| (width - 1, y)).collect(),
+                Side::Bottom => (0..width).map(|x| (x, height - 1)).collect(),
+            };
+
+            for index in cells {
+                if retain(wave, index, |t| edge.allows(tiles, t, side))? {
+                    changed.push(index);
+                }
+            }
+        }
+
+        Ok(changed)
+    }
+    fn enforce(&self, _: &[Tile<P>], _: &mut Wave, _: &[Idx], _: bool) -> Result<Vec<Idx>, Error> {
+        Ok(vec![])
+    }
+    fn window(&self, window: &Window) -> Option<Box<dyn Constraint<P>>> {
+        let mut border = Self::new();
+
+        for side in Side::Top {
+            if let Some(edge) = self.get(side).filter(|_| window.touches(side)) {
+                border.set(side, edge.clone());
+            }
+        }
+
+        // Windows away from every restricted edge are not restricted at all.
+        (border != Self::new()).then(|| Box::new(border) as Box<dyn Constraint<P>>)
+    }
+}
+
+/// Transformation that a symmetric map must look the same after
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub enum Mirror {
+    /// The map is mirrored from left to right
+    Horizontal,
+    /// The map is mirrored from top to bottom
+    Vertical,
+    /// The map is mirrored both from left to right and from top to bottom
+    Both,
+    /// The map is rotated by a half turn
+    Rotational,
+}
+
+impl Mirror {
+    /// Returns whether each transformation that the map must match mirrors it from left to right,
+    /// and from top to bottom
+    const fn mirrors(self) -> &'static [(bool, bool)] {
+        match self {
+            Self::Horizontal => &[(true, false)],
+            Self::Vertical => &[(false, true)],
+            Self::Both => &[(true, false), (false, true)],
+            Self::Rotational => &[(true, true)],
+        }
+    }
+}
+
+/// Requires the map to look the same after being mirrored or rotated.
+///
+/// Each cell must hold the mirror image of the tile within its partner cell. A tile's mirror image
+/// is preferably a variant of the same raw tile, but may be a variant of any raw tile on the same
+/// layer with mirrored nodes, so chiral tiles may be paired with a separately drawn mirror.
+/// Generation fails with [`Error::NoMirror`] if any tile has no mirror image. Cells on the axis of
+/// the map are their own partner, so they may only hold tiles that are their own mirror image.
+///
+/// Partner cells are always found within a grid, so graph generators fail with `Error::GridOnly`.
+#[derive(Clone, Debug)]
+pub struct Symmetry {
+    /// Transformation that the map must look the same after
+    mirror: Mirror,
+    /// Index of each tile's mirror image for each transformation that the map must match, found
+    /// once when the constraint is initialized
+    images: RefCell<Vec<Vec<usize>>>,
+}
+
+impl Symmetry {
+    /// Creates a new symmetry constraint for the given transformation
+    pub const fn new(mirror: Mirror) -> Self {
+        Self {
+            mirror,
+            images: RefCell::new(vec![]),
+        }
+    }
+
+    /// Returns the transformation that the map must look the same after
+    pub const fn mirror(&self) -> Mirror {
+        self.mirror
+    }
+
+    /// Returns the index of the partner of the cell at the provided index within a map of the given
+    /// size, when mirrored from left to right and from top to bottom as given
+    const fn partner((x, y): Idx, (width, height): Idx, (mirror_x, mirror_y): (bool, bool)) -> Idx {
+        (
+            if mirror_x { width - 1 - x } else { x },
+            if mirror_y { height - 1 - y } else { y },
+        )
+    }
+    /// Returns the index of each tile's mirror image for every transformation that the map must
+    /// match, or the raw index of the first tile without one
+    fn images<const P: usize>(&self, tiles: &[Tile<P>]) -> Result<Vec<Vec<usize>>, Error> {
+        self.mirror
+            .mirrors()
+            .iter()
+            .map(|&(mirror_x, mirror_y)| {
+                tiles
+                    .iter()
+                    .map(|tile| {
+                        let mut image = *tile;
+
+                        if mirror_x {
+                            image = image.mirrored_x();
+                        }
+                        if mirror_y {
+                            image = image.mirrored_y();
+                        }
+
+                        // Variants of the same raw tile are preferred over other raw tiles.
+                        let matching =
+                            |t: &Tile<P>| t.layer() == image.layer() && t.same_nodes(&image);
+
+                        tiles
+                            .iter()
+                            .position(|t| t.index() == image.index() && matching(t))
+                            .or_else(|| tiles.iter().position(matching))
+                            .ok_or(Error::NoMirror(tile.index()))
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+impl<const P: usize> Constraint<P> for Symmetry {
+    fn initialize(&self, tiles: &[Tile<P>], wave: &mut Wave, _: bool) -> Result<Vec<Idx>, Error> {
+        if !wave.is_grid() {
+            return Err(Error::GridOnly);
+        }
+
+        let size = wave.size();
+        let images = self.images(tiles)?;
+        let mut changed = vec![];
+
+        for (images, &mirror) in images.iter().zip(self.mirror.mirrors()) {
+            for y in 0..size.1 {
+                for x in 0..size.0 {
+                    if Self::partner((x, y), size, mirror) == (x, y)
+                        && retain(wave, (x, y), |t| images[t] == t)?
+                        && !changed.contains(&(x, y))
+                    {
+                        changed.push((x, y));
+                    }
+                }
+            }
+        }
+
+        *self.images.borrow_mut() = images;
+        Ok(changed)
+    }
+    fn enforce(
+        &self,
+        _: &[Tile<P>],
+        wave: &mut Wave,
+        changed: &[Idx],
+        _: bool,
+    ) -> Result<Vec<Idx>, Error> {
+        let size = wave.size();
+        let images = self.images.borrow();
+        let mut partners = vec![];
+
+        // A cell may only hold tiles whose mirror image is possible within its partner, so only the
+        // partners of changed cells can lose tiles.
+        for (images, &mirror) in images.iter().zip(self.mirror.mirrors()) {
+            for &index in changed {
+                let partner = Self::partner(index, size, mirror);
+                let allowed: Vec<usize> = wave.possible(index).collect();
+
+                if retain(wave, partner, |t| allowed.contains(&images[t]))?
+                    && !partners.contains(&partner)
+                {
+                    partners.push(partner);
+                }
+            }
+        }
+
+        Ok(partners)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utility::generation::{
+        adjacency::Adjacency,
+        fixtures::{filled, raw, rotations, tile},
+        graph::Graph,
+        solvability::{Solvability, DEFAULT_BUDGET},
+        tile::RawTile,
+        wfc::Generator,
+        Rotation,
+    };
+
+    /// Returns `true` if the end can be reached from the start through sides containing `open`
+    fn connected(map: &VecGrid<Tile<1>>, start: Idx, end: Idx, open: Node) -> bool {
+        let mut visited = vec![start];
+        let mut stack = vec![start];
+
+        while let Some(index) = stack.pop() {
+            for side in Side::Top {
+                let Some(next) = side.neighbor(index, map.size(), false) else {
+                    continue;
+                };
+                let (tile, other) = (map.get(index).unwrap(), map.get(next).unwrap());
+
+                if tile.nodes(side)[0] == open
+                    && other.nodes(side.opposite())[0] == open
+                    && !visited.contains(&next)
+                {
+                    visited.push(next);
+                    stack.push(next);
+                }
+            }
+        }
+
+        visited.contains(&end)
+    }
+
+    #[test]
+    fn path_connects_its_ends() {
+        // Walls, straight corridors, corners, dead ends, and crossings, where `0` is open.
+        let tiles =
+            rotations(&[[1; 4], [1, 0, 0, 1], [1, 0, 1, 0], [1, 0, 1, 1], [0; 4]].map(filled::<1>));
+
+        for (size, seeds) in [(5, 200), (10, 50), (16, 10)] {
+            let end = (size - 1, size - 1);
+
+            for seed in 0..seeds {
+                let mut generator = Generator::<1>::new(size, size, &tiles).with_seed(seed);
+
+                generator.constrain(Path::new((0, 0), end, Traversal::Node(0)));
+
+                let map = generator.run(false).unwrap();
+
+                assert!(connected(&map, (0, 0), end, 0), "size {size}, seed {seed}");
+            }
+        }
+    }
+
+    #[test]
+    fn path_fails_without_open_tiles() {
+        let mut generator = Generator::<1>::new(3, 1, &rotations(&[[1; 4]].map(filled::<1>)));
+
+        generator.constrain(Path::new((0, 0), (2, 0), Traversal::Node(0)));
+
+        assert_eq!(generator.run(false), Err(Error::NoPath((0, 0), (2, 0))));
+    }
+
+    #[test]
+    fn path_outside_of_the_map_is_an_error() {
+        let mut generator = Generator::<1>::new(3, 3, &rotations(&[[0; 4]].map(filled::<1>)));
+
+        generator.constrain(Path::new((0, 0), (9, 9), Traversal::Node(0)));
+
+        assert_eq!(generator.run(false), Err(Error::OutOfBounds((9, 9))));
+        assert_eq!(
+            Solvability::check(&generator, false, DEFAULT_BUDGET),
+            Solvability::Invalid(Error::OutOfBounds((9, 9)))
+        );
+    }
+
+    #[test]
+    fn path_is_skipped_within_windows() {
+        // No two of these tiles can sit side by side, so any window two cells wide fails.
+        let mut generator = Generator::<1>::new(3, 3, &[tile(0, [0, 0, 1, 0])]);
+
+        generator.constrain(Path::new((0, 0), (2, 2), Traversal::Node(0)));
+
+        assert!(generator
+            .window((1, 1), (2, 2), false)
+            .constraints()
+            .is_empty());
+
+        let Solvability::Unsolvable(example) =
+            Solvability::check(&generator, false, DEFAULT_BUDGET)
+        else {
+            panic!("expected the map to be unsolvable");
+        };
+
+        assert_eq!((example.offset(), example.size()), ((0, 0), (2, 1)));
+    }
+
+    #[test]
+    fn windows_translate_indexes() {
+        let window = Window {
+            offset: (1, 2),
+            size: (2, 2),
+            map: (3, 4),
+            wrap: false,
+        };
+
+        assert_eq!(window.local((2, 3)), Some((1, 1)));
+        assert_eq!(window.local((0, 3)), None);
+        assert_eq!(window.global((1, 0)), (2, 2));
+        assert!(!window.touches(Side::Top) && !window.touches(Side::Left));
+        assert!(window.touches(Side::Right) && window.touches(Side::Bottom));
+        assert!(!Window {
+            wrap: true,
+            ..window
+        }
+        .touches(Side::Right));
+    }
+
+    /// Returns rules that allow any tile next to any other tile
+    fn anything(tiles: usize) -> Adjacency {
+        let mut rules = Adjacency::from_example(tiles, &VecGrid::new(1, 1), false);
+
+        for tile in 0..tiles {
+            for other in 0..tiles {
+                for side in Side::Top {
+                    rules.allow(tile, side, other);
+                }
+            }
+        }
+
+        rules
+    }
+
+    /// Returns metadata where only the second raw tile is tagged as `spaced`
+    fn spaced() -> Metadata {
+        let tagged = RawTile {
+            tags: vec!["spaced".to_string()],
+            ..RawTile::default()
+        };
+
+        Metadata::new(&[RawTile::default(), tagged])
+    }
+
+    #[test]
+    fn spacing_counts_every_rotation() {
+        // Every rotation of the tagged raw tile is spaced apart from the others.
+        let tiles = rotations::<1>(&[[0; 4], [1, 0, 0, 0]].map(filled::<1>));
+
+        for seed in 0..10 {
+            let mut generator =
+                Generator::with_adjacency(6, 6, &tiles, anything(tiles.len())).with_seed(seed);
+
+            generator.constrain(Spacing::new(spaced(), "spaced", 3, Metric::Chebyshev));
+
+            let map = generator.run(false).unwrap();
+            let spaced = map
+                .indexes()
+                .into_iter()
+                .filter(|i| map.get(*i).unwrap().index() == 1);
+            let spaced: Vec<Idx> = spaced.collect();
+
+            for a in &spaced {
+                for b in spaced.iter().filter(|b| *b != a) {
+                    let distance = Metric::Chebyshev.distance(*a, *b, (6, 6), false);
+
+                    assert!(distance >= 3, "seed {seed}: {a:?} and {b:?}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn spacing_rejects_nearby_rotations() {
+        let tiles = rotations::<1>(&[[0; 4], [1, 0, 0, 0]].map(filled::<1>));
+        let mut generator = Generator::with_adjacency(3, 1, &tiles, anything(tiles.len()));
+
+        generator.constrain(Spacing::new(spaced(), "spaced", 2, Metric::Manhattan));
+        generator.pin((0, 0), 1);
+        generator.pin((1, 0), 2);
+
+        assert!(generator.run(false).is_err());
+    }
+
+    #[test]
+    fn spacing_is_kept_within_windows() {
+        let tiles = rotations::<1>(&[[0; 4], [1, 0, 0, 0]].map(filled::<1>));
+        let mut generator = Generator::with_adjacency(4, 4, &tiles, anything(tiles.len()));
+
+        generator.constrain(Spacing::new(spaced(), "spaced", 2, Metric::Chebyshev));
+        generator.pin((1, 1), 1);
+        generator.pin((2, 2), 3);
+
+        assert_eq!(
+            generator.window((1, 1), (2, 2), true).constraints().len(),
+            1
+        );
+        assert!(matches!(
+            Solvability::check(&generator, false, DEFAULT_BUDGET),
+            Solvability::Unsolvable(_)
+        ));
+    }
+
+    #[test]
+    fn spacing_requires_a_grid() {
+        let tiles = rotations::<1>(&[[0; 4], [1, 0, 0, 0]].map(filled::<1>));
+        let mut generator = Generator::from_graph(Graph::grid((3, 3), false), &tiles);
+
+        generator.constrain(Spacing::new(spaced(), "spaced", 2, Metric::Chebyshev));
+
+        assert_eq!(generator.run(false), Err(Error::GridOnly));
+        assert_eq!(
+            Solvability::check(&generator, false, DEFAULT_BUDGET),
+            Solvability::Invalid(Error::GridOnly)
+        );
+    }
+
+    #[test]
+    fn masks_are_translated_within_windows() {
+        let tiles = rotations::<1>(&[[0; 4], [1; 4]].map(filled::<1>));
+        let mut allowed = VecGrid::new(3, 3);
+        let mut generator = Generator::<1>::new(3, 3, &tiles);
+
+        allowed.insert((2, 2), vec![1]);
+        generator.constrain(Mask::new(allowed));
+        generator.pin((1, 1), 0);
+
+        let Solvability::Unsolvable(counterexample) =
+            Solvability::check(&generator, false, DEFAULT_BUDGET)
+        else {
+            panic!("the map should not be solvable");
+        };
+
+        assert_eq!(counterexample.offset(), (1, 1));
+        assert_eq!(counterexample.size(), (2, 2));
+    }
+
+    #[test]
+    fn borders_restrict_the_edges_of_the_map() {
+        let tiles = rotations::<1>(&[[0; 4], [1; 4]].map(filled::<1>));
+        let mut border = Border::new();
+        let mut generator = Generator::<1>::new(3, 3, &tiles).with_seed(0);
+
+        border.set(Side::Left, Edge::Tiles(vec![1]));
+        generator.constrain(border);
+
+        let map = generator.run(false).unwrap();
+
+        assert!(map.iter().flatten().all(|t| t.index() == 1));
+    }
+
+    #[test]
+    fn borders_only_apply_to_windows_along_the_map_edge() {
+        let tiles = rotations::<1>(&[[0; 4], [1; 4]].map(filled::<1>));
+        let mut border = Border::new();
+        let mut generator = Generator::<1>::new(4, 4, &tiles);
+
+        border.set(Side::Left, Edge::Tiles(vec![1]));
+        border.set(Side::Top, Edge::Nodes(vec![1]));
+        generator.constrain(border);
+        generator.pin((2, 2), 0);
+
+        // The pinned cell is only reached by the border through windows along the map's edges.
+        let Solvability::Unsolvable(counterexample) =
+            Solvability::check(&generator, false, DEFAULT_BUDGET)
+        else {
+            panic!("the map should not be solvable");
+        };
+
+        assert_eq!(counterexample.offset(), (0, 2));
+        assert_eq!(counterexample.size(), (3, 1));
+        assert!(
+            Solvability::check(&generator.window((1, 1), (3, 3), false), false, 100).is_solvable()
+        );
+    }
+
+    #[test]
+    fn borders_require_a_grid() {
+        let tiles = rotations::<1>(&[[0; 4], [1; 4]].map(filled::<1>));
+        let mut border = Border::new();
+        let mut generator = Generator::from_graph(Graph::grid((3, 3), false), &tiles);
+
+        border.set(Side::Left, Edge::Tiles(vec![1]));
+        generator.constrain(border);
+
+        assert_eq!(generator.run(false), Err(Error::GridOnly));
+    }
+
+    /// Returns every rotation of a blank tile, a tile with a single node in the corner of its top
+    /// side, and that tile's mirror image if `mirrored` is `true`
+    fn chiral(mirrored: bool) -> Vec<Tile<2>> {
+        let side = |top: &[Node]| raw(top, &[0, 0], &[0, 0], &[0, 0]);
+        let mut raws = vec![side(&[0, 0]), side(&[0, 1])];
+
+        if mirrored {
+            raws.push(side(&[1, 0]));
+        }
+
+        rotations(&raws)
+    }
+
+    #[test]
+    fn symmetry_pairs_chiral_tiles_with_their_mirror() {
+        let tiles = chiral(true);
+        let corner = tiles.iter().position(|t| t.index() == 1).unwrap();
+
+        for seed in 0..5 {
+            let mut generator = Generator::new(4, 3, &tiles).with_seed(seed);
+
+            generator.constrain(Symmetry::new(Mirror::Horizontal));
+            generator.pin((0, 0), corner);
+
+            let map = generator.run(false).unwrap();
+
+            assert_eq!(map.get((3, 0)).unwrap().index(), 2);
+
+            for (x, y) in map.indexes() {
+                let tile = map.get((x, y)).unwrap();
+                let partner = map.get((3 - x, y)).unwrap();
+
+                assert!(tile.mirrored_x().same_nodes(partner));
+            }
+        }
+    }
+
+    #[test]
+    fn symmetry_axis_only_holds_symmetric_tiles() {
+        let tiles = chiral(true);
+
+        for seed in 0..5 {
+            let mut generator = Generator::new(5, 3, &tiles).with_seed(seed);
+
+            generator.constrain(Symmetry::new(Mirror::Horizontal));
+
+            let map = generator.run(false).unwrap();
+
+            for y in 0..3 {
+                let tile = map.get((2, y)).unwrap();
+
+                assert!(tile.mirrored_x().same_nodes(tile), "seed {seed}");
+            }
+        }
+    }
+
+    #[test]
+    fn symmetry_requires_a_grid() {
+        let mut generator = Generator::from_graph(Graph::grid((3, 3), false), &chiral(true));
+
+        generator.constrain(Symmetry::new(Mirror::Horizontal));
+
+        assert_eq!(generator.run(false), Err(Error::GridOnly));
+    }
+
+    #[test]
+    fn symmetry_without_mirrored_tiles_is_an_error() {
+        let mut generator = Generator::new(4, 3, &chiral(false));
+
+        generator.constrain(Symmetry::new(Mirror::Vertical));
+
+        assert_eq!(generator.run(false), Err(Error::NoMirror(1)));
+    }
+
+    #[test]
+    fn rotational_symmetry_uses_rotated_variants() {
+        let mut generator = Generator::new(3, 3, &chiral(false)).with_seed(0);
+
+        generator.constrain(Symmetry::new(Mirror::Rotational));
+
+        let map = generator.run(false).unwrap();
+
+        for (x, y) in map.indexes() {
+            let tile = map.get((x, y)).unwrap();
+            let partner = map.get((2 - x, 2 - y)).unwrap();
+
+            assert_eq!(tile.index(), partner.index());
+            assert!(tile.rotated(Rotation::D180).same_nodes(partner));
+        }
+    }
+}
@@ -0,0 +1,256 @@
+use std::fmt::Display;
+
+use crate::collections::grid::Idx;
+
+use super::{
+    adjacency::Adjacency,
+    wfc::{Ban, Cause, Wave},
+    Side,
+};
+
+/// Tiles that a removed tile allowed next to it, none of which were left in the neighbour that
+/// removed it
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub struct Mismatch {
+    /// Side of the removed tile that faced the neighbour
+    side: Side,
+    /// Tiles that the removed tile allowed on that side
+    allowed: Vec<usize>,
+    /// Tiles that were left in the neighbour when the tile was removed
+    remaining: Vec<usize>,
+}
+
+impl Mismatch {
+    /// Returns the side of the removed tile that faced the neighbour
+    pub const fn side(&self) -> Side {
+        self.side
+    }
+    /// Returns the tiles that the removed tile allowed on that side
+    pub fn allowed(&self) -> &[usize] {
+        &self.allowed
+    }
+    /// Returns the tiles that were left in the neighbour when the tile was removed
+    pub fn remaining(&self) -> &[usize] {
+        &self.remaining
+    }
+}
+
+/// A single tile being removed, as part of a chain of removals
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub struct Link {
+    /// Step at which the tile was removed
+    step: usize,
+    /// The removal itself
+    ban: Ban,
+    /// Tiles that did not fit, if the tile was removed by a neighbour
+    mismatch: Option<Mismatch>,
+}
+
+impl Link {
+    /// Returns the step at which the tile was removed
+    pub const fn step(&self) -> usize {
+        self.step
+    }
+    /// Returns the removal itself
+    pub const fn ban(&self) -> Ban {
+        self.ban
+    }
+    /// Returns the tiles that did not fit, if the tile was removed by a neighbour
+    pub const fn mismatch(&self) -> Option<&Mismatch> {
+        self.mismatch.as_ref()
+    }
+}
+
+impl Display for Link {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let Ban { index, tile, cause } = self.ban;
+
+        write!(f, "step {}: tile {tile} removed from {index:?} ", self.step)?;
+
+        match (cause, &self.mismatch) {
+            (Cause::Collapsed(t), _) => write!(f, "when the cell collapsed to tile {t}"),
+            (Cause::Pinned(t), _) => write!(f, "when the cell was pinned to tile {t}"),
+            (Cause::Constraint, _) => write!(f, "by a constraint"),
+            (Cause::Neighbor(n, side), Some(m)) => write!(
+                f,
+                "since it allows {:?} on its {side:?} side, but only {:?} were left at {n:?}",
+                m.allowed, m.remaining
+            ),
+            (Cause::Neighbor(n, side), None) => write!(f, "by {n:?} on its {side:?} side"),
+        }
+    }
+}
+
+/// How a single tile was removed from the cell that ran out of tiles, and the removals that led to it
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub struct Candidate {
+    /// The removed tile
+    tile: usize,
+    /// Each removal that led to the tile being removed, starting with the tile's own removal and
+    /// ending with the collapse, pin, or constraint that started the chain
+    chain: Vec<Link>,
+}
+
+impl Candidate {
+    /// Returns the removed tile
+    pub const fn tile(&self) -> usize {
+        self.tile
+    }
+    /// Returns each removal that led to the tile being removed, starting with its own removal
+    pub fn chain(&self) -> &[Link] {
+        &self.chain
+    }
+}
+
+/// Describes how a cell ran out of possible tiles
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub struct Explanation {
+    /// Index of the cell that ran out of possible tiles
+    cell: Idx,
+    /// How each of the cell's tiles was removed
+    candidates: Vec<Candidate>,
+}
+
+impl Explanation {
+    /// Creates a new explanation of how the cell at the provided index ran out of tiles, where tiles
+    /// were removed from neighbours following the given rules
+    pub fn new(rules: &Adjacency, wave: &Wave, cell: Idx) -> Self {
+        let candidates = (0..rules.len())
+            .filter_map(|tile| {
+                let step = wave.banned_at(cell, tile)?;
+
+                Some(Candidate {
+                    tile,
+                    chain: Self::chain(rules, wave, step),
+                })
+            })
+            .collect();
+
+        Self { cell, candidates }
+    }
+
+    /// Returns the index of the cell that ran out of possible tiles
+    pub const fn cell(&self) -> Idx {
+        self.cell
+    }
+    /// Returns how each of the cell's tiles was removed
+    pub fn candidates(&self) -> &[Candidate] {
+        &self.candidates
+    }
+
+    /// Follows the removal at the given step back to the removal that started it
+    fn chain(rules: &Adjacency, wave: &Wave, mut step: usize) -> Vec<Link> {
+        let history = wave.history();
+        let mut chain = vec![];
+
+        loop {
+            let ban = history[step];
+            let Cause::Neighbor(neighbor, side) = ban.cause else {
+                chain.push(Link {
+                    step,
+                    ban,
+                    mismatch: None,
+                });
+
+                return chain;
+            };
+
+            chain.push(Link {
+                step,
+                ban,
+                mismatch: Some(Mismatch {
+                    side,
+                    allowed: rules.get(ban.tile, side).to_vec(),
+                    remaining: wave.possible_before(neighbor, step).collect(),
+                }),
+            });
+
+            // The neighbour was last changed by its most recent removal before this one.
+            match history[..step].iter().rposition(|b| b.index == neighbor) {
+                Some(previous) => step = previous,
+                None => return chain,
+            }
+        }
+    }
+}
+
+impl Display for Explanation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "cell {:?} ran out of possible tiles", self.cell)?;
+
+        for candidate in &self.candidates {
+            write!(f, "\n  tile {}:", candidate.tile)?;
+
+            for link in &candidate.chain {
+                write!(f, "\n    {link}")?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utility::generation::fixtures::tile;
+
+    /// Returns rules for two tiles that only fit next to themselves
+    fn rules() -> Adjacency {
+        Adjacency::from_nodes(&[tile(0, [0; 4]), tile(1, [1; 4])])
+    }
+
+    /// Returns a wave of two cells pinned to tiles that do not fit, where the first cell ran out of
+    /// tiles
+    fn contradiction() -> Wave {
+        let mut wave = Wave::new((2, 1), 2);
+
+        wave.collapse_because((0, 0), 0, Cause::Pinned(0));
+        wave.collapse_because((1, 0), 1, Cause::Pinned(1));
+        wave.ban_because((0, 0), 0, Cause::Neighbor((1, 0), Side::Right));
+        wave
+    }
+
+    #[test]
+    fn chains_end_where_they_started() {
+        let explanation = Explanation::new(&rules(), &contradiction(), (0, 0));
+        let [removed, pinned] = explanation.candidates() else {
+            panic!("both tiles should be candidates");
+        };
+
+        assert_eq!(explanation.cell(), (0, 0));
+        assert_eq!(pinned.tile(), 1);
+        assert_eq!(pinned.chain().len(), 1);
+        assert_eq!(pinned.chain()[0].ban().cause, Cause::Pinned(0));
+
+        assert_eq!(removed.tile(), 0);
+        assert_eq!(removed.chain().len(), 2);
+        assert_eq!(removed.chain()[1].ban().index, (1, 0));
+        assert_eq!(removed.chain()[1].ban().cause, Cause::Pinned(1));
+    }
+
+    #[test]
+    fn neighbours_describe_their_mismatch() {
+        let explanation = Explanation::new(&rules(), &contradiction(), (0, 0));
+        let link = &explanation.candidates()[0].chain()[0];
+        let mismatch = link.mismatch().unwrap();
+
+        assert_eq!(mismatch.side(), Side::Right);
+        assert_eq!(mismatch.allowed(), &[0]);
+        assert_eq!(mismatch.remaining(), &[1]);
+        assert!(link
+            .to_string()
+            .ends_with("allows [0] on its Right side, but only [1] were left at (1, 0)"));
+    }
+
+    #[test]
+    fn cells_with_tiles_left_have_no_candidates() {
+        let explanation = Explanation::new(&rules(), &Wave::new((1, 1), 2), (0, 0));
+
+        assert!(explanation.candidates().is_empty());
+        assert_eq!(
+            explanation.to_string(),
+            "cell (0, 0) ran out of possible tiles"
+        );
+    }
+}
@@ -0,0 +1,43 @@
+use super::tile::{Node, RawTile, Tile, TileSet};
+
+/// Returns a raw tile with a weight of `1` and the given nodes on its top, left, right, and bottom
+/// sides
+pub fn raw(top: &[Node], left: &[Node], right: &[Node], bottom: &[Node]) -> RawTile {
+    RawTile {
+        weight: 1,
+        nodes: (top.to_vec(), left.to_vec(), right.to_vec(), bottom.to_vec()),
+        ..RawTile::default()
+    }
+}
+
+/// Returns a raw tile with a weight of `1` and the given node along every position of its top,
+/// left, right, and bottom sides
+pub fn filled<const P: usize>([top, left, right, bottom]: [Node; 4]) -> RawTile {
+    raw(&[top; P], &[left; P], &[right; P], &[bottom; P])
+}
+
+/// Returns an unrotated tile with a single node on its top, left, right, and bottom sides
+pub fn tile(index: usize, nodes: [Node; 4]) -> Tile<1> {
+    Tile::from_raw(index, &filled::<1>(nodes)).unwrap()
+}
+
+/// Returns every distinct rotation of the provided raw tiles, in the order that a tile set adds
+/// them
+pub fn rotations<const P: usize>(raws: &[RawTile]) -> Vec<Tile<P>> {
+    let mut set = TileSet::new(0);
+
+    set.add_all_raws(raws);
+    set.tiles().to_vec()
+}
+
+/// Returns an unrotated tile for every combination of sides containing node `1` rather than `0`,
+/// indexed by bits set from lowest to highest for its top, left, right, and bottom sides
+pub fn openings() -> Vec<Tile<1>> {
+    (0..16)
+        .map(|i| {
+            let open = |bit: usize| u8::from(i & (1 << bit) != 0);
+
+            tile(i, [open(0), open(1), open(2), open(3)])
+        })
+        .collect()
+}
@@ -0,0 +1,247 @@
+use std::{fmt::Display, path::Path};
+
+use ron::ser::PrettyConfig;
+use serde::{de::DeserializeOwned, Serialize};
+
+/// Bytes at the start of every binary file
+pub const MAGIC: [u8; 4] = *b"WFC\x01";
+
+/// Error that may occur while encoding or decoding a file
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub enum Error {
+    /// The data could not be decoded, stored as the reason
+    Decode(String),
+    /// The value could not be encoded, stored as the reason
+    Encode(String),
+    /// The data does not start with the binary format's magic bytes
+    NotBinary,
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Decode(reason) => write!(f, "could not decode: {reason}"),
+            Self::Encode(reason) => write!(f, "could not encode: {reason}"),
+            Self::NotBinary => write!(f, "data is not in the binary format"),
+        }
+    }
+}
+
+/// Format that tile files and maps may be stored in
+#[derive(Clone, Copy, Debug, Default, Hash, PartialEq, Eq)]
+pub enum Format {
+    /// Rusty Object Notation, using the `.ron` extension
+    #[default]
+    Ron,
+    /// JavaScript Object Notation, using the `.json` extension
+    Json,
+    /// Compact binary encoding starting with `MAGIC`, using the `.bin` extension
+    Binary,
+}
+
+impl Format {
+    /// Returns the format that uses the extension of the provided path, if any
+    pub fn from_extension(path: impl AsRef<Path>) -> Option<Self> {
+        match path.as_ref().extension()?.to_str()? {
+            "ron" => Some(Self::Ron),
+            "json" => Some(Self::Json),
+            "bin" => Some(Self::Binary),
+            _ => None,
+        }
+    }
+    /// Returns the format of the provided data, where data that does not start with `MAGIC` is
+    /// JSON if it starts with an object or array, and RON otherwise
+    pub fn detect(bytes: &[u8]) -> Self {
+        if bytes.starts_with(&MAGIC) {
+            return Self::Binary;
+        }
+
+        match bytes.iter().find(|b| !b.is_ascii_whitespace()) {
+            Some(b'{' | b'[') => Self::Json,
+            _ => Self::Ron,
+        }
+    }
+    /// Returns the format of the provided data loaded from the given path, where binary data is
+    /// always detected by its magic bytes, and other data by the path's extension if it has one
+    pub fn of(path: impl AsRef<Path>, bytes: &[u8]) -> Self {
+        match Self::detect(bytes) {
+            Self::Binary => Self::Binary,
+            detected => Self::from_extension(path).unwrap_or(detected),
+        }
+    }
+
+    /// Returns the extension used by files of this format
+    pub const fn extension(self) -> &'static str {
+        match self {
+            Self::Ron => "ron",
+            Self::Json => "json",
+            Self::Binary => "bin",
+        }
+    }
+
+    /// Decodes a value from the provided data
+    pub fn decode<T: DeserializeOwned>(self, bytes: &[u8]) -> Result<T, Error> {
+        let decode = |e: &dyn Display| Error::Decode(e.to_string());
+
+        match self {
+            Self::Ron => {
+                let source = std::str::from_utf8(bytes).map_err(|e| decode(&e))?;
+
+                ron::from_str(source).map_err(|e| decode(&e))
+            }
+            Self::Json => serde_json::from_slice(bytes).map_err(|e| decode(&e)),
+            Self::Binary => {
+                let bytes = bytes.strip_prefix(&MAGIC).ok_or(Error::NotBinary)?;
+
+                bincode::deserialize(bytes).map_err(|e| decode(&e))
+            }
+        }
+    }
+    /// Encodes the provided value
+    pub fn encode<T: Serialize>(self, value: &T) -> Result<Vec<u8>, Error> {
+        let encode = |e: &dyn Display| Error::Encode(e.to_string());
+
+        match self {
+            // Whole floats keep their decimal point, so that they are not read back as integers
+            Self::Ron => {
+                let config = PrettyConfig::default().decimal_floats(true);
+
+                ron::ser::to_string_pretty(value, config)
+                    .map(String::into_bytes)
+                    .map_err(|e| encode(&e))
+            }
+            Self::Json => serde_json::to_vec_pretty(value).map_err(|e| encode(&e)),
+            Self::Binary => {
+                let mut bytes = MAGIC.to_vec();
+
+                bincode::serialize_into(&mut bytes, value).map_err(|e| encode(&e))?;
+                Ok(bytes)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utility::generation::{
+        metadata::Property,
+        tile::{RawFile, RawMap, RawTile},
+        Rotation,
+    };
+
+    const FORMATS: [Format; 3] = [Format::Ron, Format::Json, Format::Binary];
+
+    /// Returns a file with a single tile, using optional and nested fields and every type of
+    /// property
+    fn file() -> RawFile {
+        RawFile {
+            id: 4,
+            version: 3,
+            precision: 2,
+            extends: Some("base.ron".to_string()),
+            weights: vec![("grass".to_string(), 2)],
+            tiles: vec![RawTile {
+                name: "grass".to_string(),
+                weight: 5,
+                nodes: (vec![1, 2], vec![3, 4], vec![5, 6], vec![7, 8]),
+                tags: vec!["walkable".to_string()],
+                properties: [
+                    ("lit".to_string(), Property::Bool(true)),
+                    ("cost".to_string(), Property::Int(-2)),
+                    ("speed".to_string(), Property::Float(2.0)),
+                    ("sound".to_string(), Property::String("rustle".to_string())),
+                ]
+                .into(),
+                ..RawTile::default()
+            }],
+            ..RawFile::default()
+        }
+    }
+
+    #[test]
+    fn formats_are_found_from_extensions() {
+        for format in FORMATS {
+            let path = format!("tiles/test.{}", format.extension());
+
+            assert_eq!(Format::from_extension(path), Some(format));
+        }
+
+        assert_eq!(Format::from_extension("tiles/test.png"), None);
+        assert_eq!(Format::from_extension("tiles/test"), None);
+    }
+
+    #[test]
+    fn formats_are_detected_from_data() {
+        assert_eq!(Format::detect(b"WFC\x01..."), Format::Binary);
+        assert_eq!(Format::detect(b"  \n{\"id\": 0}"), Format::Json);
+        assert_eq!(Format::detect(b"[1, 2]"), Format::Json);
+        assert_eq!(Format::detect(b"(id: 0)"), Format::Ron);
+        assert_eq!(Format::detect(b""), Format::Ron);
+
+        // Extensions win over detection, except for binary data.
+        assert_eq!(Format::of("test.ron", b"[1, 2]"), Format::Ron);
+        assert_eq!(Format::of("test.json", b"WFC\x01"), Format::Binary);
+        assert_eq!(Format::of("test", b"{}"), Format::Json);
+    }
+
+    #[test]
+    fn files_and_maps_round_trip() {
+        let map = RawMap {
+            id: 1,
+            width: 2,
+            height: 1,
+            cells: vec![Some((3, Rotation::D270)), None],
+        };
+
+        for format in FORMATS {
+            let bytes = format.encode(&file()).unwrap();
+
+            assert_eq!(Format::detect(&bytes), format);
+            assert_eq!(format.decode::<RawFile>(&bytes), Ok(file()));
+            assert_eq!(
+                format.decode::<RawMap>(&format.encode(&map).unwrap()),
+                Ok(map.clone())
+            );
+        }
+    }
+
+    #[test]
+    fn empty_fields_are_left_out_of_text() {
+        let file = RawFile {
+            tiles: vec![RawTile::default()],
+            ..RawFile::default()
+        };
+
+        for format in [Format::Ron, Format::Json] {
+            let text = String::from_utf8(format.encode(&file).unwrap()).unwrap();
+
+            assert!(!text.contains("include") && !text.contains("interior"));
+            assert_eq!(format.decode::<RawFile>(text.as_bytes()), Ok(file.clone()));
+        }
+    }
+
+    #[test]
+    fn invalid_data_is_an_error() {
+        assert_eq!(
+            Format::Binary.decode::<RawFile>(b"(id: 0)"),
+            Err(Error::NotBinary)
+        );
+
+        let mut truncated = Format::Binary.encode(&file()).unwrap();
+
+        truncated.truncate(truncated.len() / 2);
+
+        for (format, bytes) in [
+            (Format::Ron, &b"(id: \xff)"[..]),
+            (Format::Ron, b"(id: 0"),
+            (Format::Json, b"{\"id\": 0"),
+            (Format::Binary, &truncated),
+        ] {
+            assert!(matches!(
+                format.decode::<RawFile>(bytes),
+                Err(Error::Decode(_))
+            ));
+        }
+    }
+}
@@ -0,0 +1,269 @@
+use crate::collections::grid::{vec::VecGrid, Grid, Idx};
+
+use super::{
+    tile::Tile,
+    wfc::{Error, Wave},
+};
+
+/// Target share of a generated map that each raw tile should cover
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Frequency {
+    /// Share of the map for each raw tile, indexed by raw tile and adding up to one
+    shares: Vec<f64>,
+    /// Largest allowed difference between a raw tile's share and its target, as a share of the map
+    tolerance: f64,
+    /// Number of times a map is generated before giving up on matching the targets
+    attempts: usize,
+}
+
+impl Frequency {
+    /// Creates a new target from the relative share of each raw tile, indexed by raw tile.
+    ///
+    /// `tolerance` is the largest allowed difference from each target, as a share of the whole map.
+    pub fn new(shares: &[f64], tolerance: f64) -> Self {
+        let total: f64 = shares.iter().map(|s| s.max(0.0)).sum();
+        let shares = shares
+            .iter()
+            .map(|s| if total > 0.0 { s.max(0.0) / total } else { 0.0 })
+            .collect();
+
+        Self {
+            shares,
+            tolerance,
+            attempts: 1,
+        }
+    }
+    /// Creates a new target from the weight of each raw tile that the provided tiles were created from
+    pub fn from_weights<const P: usize>(tiles: &[Tile<P>], tolerance: f64) -> Self {
+        let raws = tiles.iter().map(|t| t.index() + 1).max().unwrap_or(0);
+        let mut shares = vec![0.0; raws];
+
+        for tile in tiles {
+            shares[tile.index()] = f64::from(tile.weight());
+        }
+
+        Self::new(&shares, tolerance)
+    }
+
+    /// Sets the number of times a map may be generated before failing because a raw tile's share is
+    /// outside of the tolerance, which defaults to `1`
+    pub const fn with_attempts(mut self, attempts: usize) -> Self {
+        self.attempts = attempts;
+        self
+    }
+
+    /// Returns the target share of the map for the given raw tile
+    pub fn share(&self, raw: usize) -> f64 {
+        self.shares.get(raw).copied().unwrap_or(0.0)
+    }
+    /// Returns the largest allowed difference from each target, as a share of the map
+    pub const fn tolerance(&self) -> f64 {
+        self.tolerance
+    }
+    /// Returns the number of times a map may be generated, which is always at least `1`
+    pub fn attempts(&self) -> usize {
+        self.attempts.max(1)
+    }
+    /// Returns the number of cells that should use the given raw tile within a map of `cells` cells
+    pub fn target(&self, raw: usize, cells: usize) -> usize {
+        (self.share(raw) * cells as f64).round() as usize
+    }
+
+    /// Returns the largest allowed difference from each target within a map of `cells` cells
+    fn allowed(&self, cells: usize) -> usize {
+        (self.tolerance * cells as f64).ceil() as usize
+    }
+
+    /// Returns the number of cells using each raw tile within the provided map
+    pub fn histogram<const P: usize>(&self, map: &VecGrid<Tile<P>>) -> Vec<usize> {
+        let mut counts = vec![0; self.shares.len()];
+
+        for index in map.indexes() {
+            if let Some(count) = map.get(index).and_then(|t| counts.get_mut(t.index())) {
+                *count += 1;
+            }
+        }
+
+        counts
+    }
+    /// Returns an error if any raw tile's share of the provided map differs from its target by more
+    /// than the tolerance
+    pub fn check<const P: usize>(&self, map: &VecGrid<Tile<P>>) -> Result<(), Error> {
+        let cells = map.capacity();
+        let allowed = self.allowed(cells);
+
+        for (raw, count) in self.histogram(map).into_iter().enumerate() {
+            let target = self.target(raw, cells);
+
+            if count.abs_diff(target) > allowed {
+                return Err(Error::OffTarget(raw, count, target));
+            }
+        }
+
+        Ok(())
+    }
+    /// Returns an error as soon as any raw tile covers more cells of a partly generated map than its
+    /// tolerance allows, since the finished map can only cover more
+    pub fn check_tally(&self, tally: &Tally, cells: usize) -> Result<(), Error> {
+        let allowed = self.allowed(cells);
+
+        for (raw, count) in tally.counts.iter().enumerate() {
+            let target = self.target(raw, cells);
+
+            if *count > target + allowed {
+                return Err(Error::OffTarget(raw, *count, target));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Running count of the cells of a wave that are left with a single tile, for each raw tile
+#[derive(Clone, Debug, Default, Hash, PartialEq, Eq)]
+pub struct Tally {
+    /// Number of settled cells using each raw tile, indexed by raw tile
+    counts: Vec<usize>,
+    /// Whether each cell has been counted, in row-major order
+    settled: Vec<bool>,
+    /// Number of the wave's bans that have already been counted
+    seen: usize,
+}
+
+impl Tally {
+    /// Creates a new tally of every cell of the provided wave that is left with a single tile
+    pub fn new<const P: usize>(tiles: &[Tile<P>], wave: &Wave) -> Self {
+        let (width, height) = wave.size();
+        let mut tally = Self {
+            counts: vec![0; tiles.iter().map(|t| t.index() + 1).max().unwrap_or(0)],
+            settled: vec![false; width * height],
+            seen: wave.history().len(),
+        };
+
+        for y in 0..height {
+            for x in 0..width {
+                tally.settle(tiles, wave, (x, y));
+            }
+        }
+
+        tally
+    }
+
+    /// Returns the number of settled cells using the given raw tile
+    pub fn count(&self, raw: usize) -> usize {
+        self.counts.get(raw).copied().unwrap_or(0)
+    }
+    /// Counts every cell that has been left with a single tile since the tally was last updated,
+    /// only visiting the cells changed by the wave's newest bans
+    pub fn update<const P: usize>(&mut self, tiles: &[Tile<P>], wave: &Wave) {
+        let history = wave.history();
+
+        for ban in &history[self.seen.min(history.len())..] {
+            self.settle(tiles, wave, ban.index);
+        }
+
+        self.seen = history.len();
+    }
+
+    /// Counts the cell at the provided index if it has been left with a single tile
+    fn settle<const P: usize>(&mut self, tiles: &[Tile<P>], wave: &Wave, (x, y): Idx) {
+        let position = y * wave.size().0 + x;
+
+        if self.settled[position] || wave.count((x, y)) != 1 {
+            return;
+        }
+        if let Some(tile) = wave.possible((x, y)).next() {
+            self.settled[position] = true;
+            self.counts[tiles[tile].index()] += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utility::generation::{fixtures::tile, wfc::Generator};
+
+    #[test]
+    fn shares_are_normalized() {
+        let frequency = Frequency::new(&[1.0, 3.0, -2.0], 0.1);
+
+        assert_eq!(frequency.share(0), 0.25);
+        assert_eq!(frequency.share(1), 0.75);
+        assert_eq!(frequency.share(2), 0.0);
+        assert_eq!(frequency.share(3), 0.0);
+        assert_eq!(frequency.target(1, 10), 8);
+    }
+
+    #[test]
+    fn tallies_count_settled_cells() {
+        let tiles = [tile(0, [0; 4]), tile(1, [0; 4])];
+        let mut wave = Wave::new((2, 2), 2);
+        let mut tally = Tally::new(&tiles, &wave);
+
+        wave.collapse((0, 0), 1);
+        wave.collapse((1, 1), 1);
+        wave.ban((0, 1), 0);
+        tally.update(&tiles, &wave);
+
+        assert_eq!(tally.count(0), 0);
+        assert_eq!(tally.count(1), 3);
+
+        // Cells are only counted once, however many more bans they receive.
+        wave.ban((0, 0), 1);
+        tally.update(&tiles, &wave);
+
+        assert_eq!(tally.count(1), 3);
+    }
+
+    #[test]
+    fn generated_maps_match_their_targets() {
+        let tiles = [tile(0, [0; 4]), tile(1, [0; 4])];
+
+        for seed in 0..5 {
+            let mut generator = Generator::new(10, 10, &tiles).with_seed(seed);
+
+            generator.match_frequency(Frequency::new(&[1.0, 3.0], 0.05));
+
+            let map = generator.run(false).unwrap();
+            let counts = Frequency::new(&[1.0, 3.0], 0.05).histogram(&map);
+
+            assert!(counts[0].abs_diff(25) <= 5, "seed {seed}: {counts:?}");
+        }
+    }
+
+    #[test]
+    fn unreachable_targets_fail_after_every_attempt() {
+        let tiles = [tile(0, [0; 4]), tile(1, [0; 4])];
+        let mut generator = Generator::new(4, 1, &tiles).with_seed(0);
+
+        generator.pin((0, 0), 0);
+        generator.pin((1, 0), 0);
+        generator.match_frequency(Frequency::new(&[0.0, 1.0], 0.0).with_attempts(3));
+
+        assert_eq!(generator.run(false), Err(Error::OffTarget(0, 2, 0)));
+    }
+
+    #[test]
+    fn partial_maps_fail_once_over_their_target() {
+        let frequency = Frequency::new(&[1.0, 1.0], 0.0);
+        let tiles = [tile(0, [0; 4]), tile(1, [0; 4])];
+        let mut wave = Wave::new((4, 1), 2);
+
+        for x in 0..2 {
+            wave.collapse((x, 0), 0);
+        }
+
+        let mut tally = Tally::new(&tiles, &wave);
+
+        assert_eq!(frequency.check_tally(&tally, 4), Ok(()));
+
+        wave.collapse((2, 0), 0);
+        tally.update(&tiles, &wave);
+
+        assert_eq!(
+            frequency.check_tally(&tally, 4),
+            Err(Error::OffTarget(0, 3, 2))
+        );
+    }
+}
@@ -0,0 +1,142 @@
+use crate::collections::grid::Idx;
+
+use super::Side;
+
+/// Cells connected by edges, where each edge is labelled with the side of the tile it leaves through.
+///
+/// Connecting two cells always connects them in both directions, labelling the reverse edge with
+/// the opposite side, meaning tiles are matched in the same way as neighbours within a grid.
+#[derive(Clone, Debug, Default, Hash, PartialEq, Eq)]
+pub struct Graph(Vec<Vec<(Side, usize)>>);
+
+impl Graph {
+    /// Creates a new graph with the given number of unconnected cells
+    pub fn new(cells: usize) -> Self {
+        Self(vec![vec![]; cells])
+    }
+    /// Creates a new graph with the same shape as a grid of the provided size, where cells are stored
+    /// in row-major order.
+    ///
+    /// If `wrap` is `true`, cells on opposite edges of the grid are connected.
+    pub fn grid((width, height): Idx, wrap: bool) -> Self {
+        let mut graph = Self::new(width * height);
+
+        for y in 0..height {
+            for x in 0..width {
+                for side in Side::Top {
+                    if let Some((nx, ny)) = side.neighbor((x, y), (width, height), wrap) {
+                        graph.0[y * width + x].push((side, ny * width + nx));
+                    }
+                }
+            }
+        }
+
+        graph
+    }
+
+    /// Returns the total number of cells in the graph
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+    /// Returns `true` if the graph contains no cells
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+    /// Returns the cells connected to the provided cell, and the side they are connected through
+    pub fn neighbors(&self, cell: usize) -> &[(Side, usize)] {
+        &self.0[cell]
+    }
+
+    /// Adds a new unconnected cell to the graph, returning its index
+    pub fn add_cell(&mut self) -> usize {
+        self.0.push(vec![]);
+        self.0.len() - 1
+    }
+    /// Connects the given side of the `from` cell to the opposite side of the `to` cell
+    pub fn connect(&mut self, from: usize, side: Side, to: usize) {
+        self.0[from].push((side, to));
+        self.0[to].push((side.opposite(), from));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        collections::grid::Grid,
+        utility::generation::{
+            fixtures::tile,
+            wfc::{Error, Generator},
+        },
+    };
+
+    #[test]
+    fn grids_connect_neighbours() {
+        let graph = Graph::grid((3, 2), false);
+
+        assert_eq!(graph.len(), 6);
+        assert_eq!(graph.neighbors(0), &[(Side::Right, 1), (Side::Bottom, 3)]);
+        assert_eq!(graph.neighbors(4).len(), 3);
+    }
+
+    #[test]
+    fn wrapping_grids_connect_opposite_edges() {
+        let graph = Graph::grid((3, 2), true);
+
+        assert!(graph.neighbors(0).contains(&(Side::Left, 2)));
+        assert!(graph.neighbors(0).contains(&(Side::Top, 3)));
+        assert!((0..graph.len()).all(|cell| graph.neighbors(cell).len() == 4));
+    }
+
+    #[test]
+    fn connections_go_both_ways() {
+        let mut graph = Graph::new(1);
+        let cell = graph.add_cell();
+
+        graph.connect(0, Side::Right, cell);
+
+        assert_eq!(graph.neighbors(0), &[(Side::Right, 1)]);
+        assert_eq!(graph.neighbors(cell), &[(Side::Left, 0)]);
+    }
+
+    #[test]
+    fn generated_tiles_fit_along_edges() {
+        // A ring of four cells, where tiles alternate around the ring.
+        let mut graph = Graph::new(4);
+
+        for cell in 0..4 {
+            graph.connect(cell, Side::Right, (cell + 1) % 4);
+        }
+
+        let tiles = [tile(0, [0, 1, 2, 0]), tile(1, [0, 2, 1, 0])];
+        let map = Generator::from_graph(graph.clone(), &tiles)
+            .with_seed(0)
+            .run(false)
+            .unwrap();
+
+        assert_eq!(map.size(), (4, 1));
+
+        for cell in 0..graph.len() {
+            let tile = map.get((cell, 0)).unwrap();
+
+            for (side, other) in graph.neighbors(cell) {
+                assert!(tile.fits(map.get((*other, 0)).unwrap(), *side));
+            }
+        }
+    }
+
+    #[test]
+    fn graphs_without_a_solution_are_exhausted() {
+        // Odd rings can not alternate between two tiles.
+        let mut graph = Graph::new(3);
+
+        graph.connect(0, Side::Right, 1);
+        graph.connect(1, Side::Right, 2);
+        graph.connect(2, Side::Right, 0);
+
+        let tiles = [tile(0, [0, 1, 2, 0]), tile(1, [0, 2, 1, 0])];
+        let result = Generator::from_graph(graph, &tiles).with_seed(0).run(false);
+
+        assert!(matches!(result, Err(Error::Exhausted(_))));
+    }
+}
@@ -0,0 +1,260 @@
+use std::fmt::Debug;
+
+use bevy::utils::HashMap;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use crate::collections::grid::{vec::VecGrid, Grid, Idx};
+
+use super::{
+    constraint::Mask,
+    tile::Tile,
+    wfc::{Error, Generator},
+    Side,
+};
+
+/// Additional setup of the generator that fills regions with detail tiles, such as constraints and
+/// pinned cells, applied once the map of regions is known
+pub trait Details<const R: usize, const P: usize>: Debug {
+    /// Configures the provided detail generator for the given map of regions.
+    ///
+    /// The generator's tiles are every region's detail tiles, in order of their region's raw index.
+    fn configure(&self, details: &mut Generator<P>, regions: &VecGrid<Tile<R>>);
+}
+
+/// Generates a map in two passes, first generating a coarse map of regions, and then filling each
+/// region with detail tiles chosen by the region's tile.
+///
+/// Every region is filled within a single detail map, so tiles on either side of the border between
+/// two regions always fit together. If they can not, the map of regions may be generated again.
+#[derive(Debug)]
+pub struct Hierarchy<const R: usize, const P: usize> {
+    /// Generator used to create the map of regions
+    regions: Generator<R>,
+    /// Detail tiles used within each region, keyed by the raw index of the region's tile
+    details: HashMap<usize, Vec<Tile<P>>>,
+    /// Additional setup of the detail generator
+    setup: Vec<Box<dyn Details<R, P>>>,
+    /// Number of detail cells horizontally and vertically within each region
+    scale: (usize, usize),
+    /// Number of times the map of regions is generated before giving up on the border between two
+    /// regions
+    attempts: usize,
+    /// Random number generator used to seed the detail generator
+    rng: StdRng,
+}
+
+impl<const R: usize, const P: usize> Hierarchy<R, P> {
+    /// Creates a new hierarchical generator, where each cell of the region map is made up of the
+    /// given number of detail cells
+    pub fn new(regions: Generator<R>, scale: (usize, usize)) -> Self {
+        Self {
+            regions,
+            details: HashMap::new(),
+            setup: vec![],
+            scale,
+            attempts: 1,
+            rng: StdRng::from_entropy(),
+        }
+    }
+    /// Sets the seed of both passes' random number generators
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.regions = self.regions.with_seed(seed);
+        self.rng = StdRng::seed_from_u64(seed);
+        self
+    }
+    /// Sets the number of times the map of regions may be generated, which defaults to `1`.
+    ///
+    /// The map of regions is only generated again when the detail tiles run out along the border
+    /// between two regions.
+    pub const fn with_attempts(mut self, attempts: usize) -> Self {
+        self.attempts = attempts;
+        self
+    }
+
+    /// Sets the detail tiles used within regions whose tile was created from the given raw tile
+    pub fn add_region(&mut self, region: usize, tiles: &[Tile<P>]) {
+        self.details.insert(region, tiles.to_vec());
+    }
+    /// Adds setup that is applied to the detail generator before each detail pass
+    pub fn configure<D: Details<R, P> + 'static>(&mut self, details: D) {
+        self.setup.push(Box::new(details));
+    }
+    /// Returns a mutable reference to the generator used to create the map of regions
+    pub fn regions_mut(&mut self) -> &mut Generator<R> {
+        &mut self.regions
+    }
+    /// Returns every region's detail tiles in order of their region's raw index, as used by the
+    /// detail generator
+    pub fn detail_tiles(&self) -> Vec<Tile<P>> {
+        self.combine().0
+    }
+
+    /// Runs both passes, returning the map of regions and the map of detail tiles
+    pub fn run(&mut self, wrap: bool) -> Result<(VecGrid<Tile<R>>, VecGrid<Tile<P>>), Error> {
+        let mut attempt = 1;
+
+        loop {
+            let regions = self.regions.run(wrap)?;
+
+            match self.fill(&regions, wrap) {
+                Ok(details) => return Ok((regions, details)),
+                Err(Error::Exhausted(explanation))
+                    if attempt < self.attempts && self.on_border(&regions, explanation.cell()) =>
+                {
+                    attempt += 1;
+                }
+                Err(error) => return Err(error),
+            }
+        }
+    }
+
+    /// Combines every region's detail tiles into a single list, returning the list and the
+    /// indexes of each region's tiles within it
+    fn combine(&self) -> (Vec<Tile<P>>, HashMap<usize, Vec<usize>>) {
+        let mut keys: Vec<usize> = self.details.keys().copied().collect();
+        let mut tiles = vec![];
+        let mut ranges = HashMap::new();
+
+        keys.sort_unstable();
+
+        for key in keys {
+            let start = tiles.len();
+
+            tiles.extend_from_slice(&self.details[&key]);
+            ranges.insert(key, (start..tiles.len()).collect::<Vec<_>>());
+        }
+
+        (tiles, ranges)
+    }
+    /// Fills each region of the provided map with detail tiles, failing if any cell of the map has
+    /// no region tile
+    fn fill(&mut self, regions: &VecGrid<Tile<R>>, wrap: bool) -> Result<VecGrid<Tile<P>>, Error> {
+        let (width, height) = regions.size();
+        let (sx, sy) = self.scale;
+        let (tiles, ranges) = self.combine();
+        let mut allowed = VecGrid::new(width * sx, height * sy);
+
+        for index in allowed.indexes() {
+            let cell = (index.0 / sx, index.1 / sy);
+            let region = regions.get(cell).ok_or(Error::EmptyRegion(cell))?.index();
+            let range = ranges.get(&region).ok_or(Error::MissingRegion(region))?;
+
+            allowed.insert(index, range.clone());
+        }
+
+        let mut details = Generator::new(width * sx, height * sy, &tiles).with_seed(self.rng.gen());
+
+        details.constrain(Mask::new(allowed));
+
+        for setup in &self.setup {
+            setup.configure(&mut details, regions);
+        }
+
+        details.run(wrap)
+    }
+    /// Returns `true` if the detail cell at the provided index borders a cell of a different region
+    fn on_border(&self, regions: &VecGrid<Tile<R>>, (x, y): Idx) -> bool {
+        let (sx, sy) = self.scale;
+        let region = |(x, y): Idx| regions.get((x / sx, y / sy)).map(Tile::index);
+        let size = (regions.size().0 * sx, regions.size().1 * sy);
+
+        Side::Top.into_iter().any(|side| {
+            side.neighbor((x, y), size, false)
+                .is_some_and(|n| region(n) != region((x, y)))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utility::generation::fixtures::tile;
+
+    /// Returns a hierarchy of two region tiles that fit anywhere, whose detail tiles never fit next
+    /// to each other
+    fn hierarchy() -> Hierarchy<1, 1> {
+        let regions = Generator::new(2, 1, &[tile(0, [0; 4]), tile(1, [0; 4])]);
+        let mut hierarchy = Hierarchy::new(regions, (2, 2));
+
+        hierarchy.add_region(0, &[tile(0, [0; 4]), tile(1, [0; 4])]);
+        hierarchy.add_region(1, &[tile(0, [1; 4])]);
+        hierarchy
+    }
+
+    /// Pins the first detail cell to the first region's second detail tile
+    #[derive(Debug)]
+    struct PinCorner;
+
+    impl Details<1, 1> for PinCorner {
+        fn configure(&self, details: &mut Generator<1>, _: &VecGrid<Tile<1>>) {
+            details.pin((0, 0), 1);
+        }
+    }
+
+    #[test]
+    fn regions_are_filled_with_their_details() {
+        for seed in 0..5 {
+            let (regions, details) = hierarchy()
+                .with_seed(seed)
+                .with_attempts(50)
+                .run(false)
+                .unwrap();
+
+            assert_eq!(details.size(), (4, 2));
+
+            for (x, y) in details.indexes() {
+                let region = regions.get((x / 2, y / 2)).unwrap().index();
+
+                assert_eq!(
+                    details.get((x, y)).unwrap().nodes(Side::Top),
+                    &[region as u8]
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn details_are_configured() {
+        let mut hierarchy = hierarchy().with_seed(0);
+
+        hierarchy.regions_mut().pin((0, 0), 0);
+        hierarchy.regions_mut().pin((1, 0), 0);
+        hierarchy.configure(PinCorner);
+
+        let (_, details) = hierarchy.run(false).unwrap();
+
+        assert_eq!(details.get((0, 0)), Some(&tile(1, [0; 4])));
+    }
+
+    #[test]
+    fn regions_are_retried_a_limited_number_of_times() {
+        let mut hierarchy = hierarchy().with_attempts(5);
+
+        hierarchy.regions_mut().pin((0, 0), 0);
+        hierarchy.regions_mut().pin((1, 0), 1);
+
+        assert!(matches!(hierarchy.run(false), Err(Error::Exhausted(_))));
+    }
+
+    #[test]
+    fn regions_without_details_are_an_error() {
+        let regions = Generator::new(1, 1, &[tile(3, [0; 4])]);
+        let mut hierarchy = Hierarchy::<1, 1>::new(regions, (1, 1));
+
+        hierarchy.add_region(0, &[tile(0, [0; 4])]);
+
+        assert_eq!(hierarchy.run(false), Err(Error::MissingRegion(3)));
+    }
+
+    #[test]
+    fn empty_regions_are_an_error() {
+        let mut regions = VecGrid::new(2, 1);
+
+        regions.insert((0, 0), tile(0, [0; 4]));
+
+        assert_eq!(
+            hierarchy().fill(&regions, false),
+            Err(Error::EmptyRegion((1, 0)))
+        );
+    }
+}
@@ -0,0 +1,423 @@
+use std::{
+    fmt::Display,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use super::{
+    migration::{self, Migration},
+    tile::{RawFile, RawTile},
+};
+
+/// Error that may occur while loading a tile file and the files it inherits from
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub enum Error {
+    /// The file at the given path could not be loaded
+    Load(PathBuf, migration::Error),
+    /// The files at the given paths include each other, starting and ending with the same file
+    Cycle(Vec<PathBuf>),
+    /// The file at the given path refers to an inherited tile that does not exist
+    UnknownTile(PathBuf, String),
+    /// The file at the given path removes or reweights inherited tiles without naming them, which
+    /// would match every unnamed tile
+    EmptyName(PathBuf),
+    /// The file at the given path has a different precision than the file inheriting from it,
+    /// stored as the file's precision and the inheriting file's precision
+    Precision(PathBuf, usize, usize),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Load(path, error) => write!(f, "{}: {error}", path.display()),
+            Self::Cycle(paths) => {
+                write!(f, "files include each other: ")?;
+
+                for (index, path) in paths.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, " -> ")?;
+                    }
+
+                    write!(f, "{}", path.display())?;
+                }
+
+                Ok(())
+            }
+            Self::UnknownTile(path, name) => {
+                write!(f, "{}: no inherited tile named {name:?}", path.display())
+            }
+            Self::EmptyName(path) => {
+                write!(f, "{}: inherited tiles must be named", path.display())
+            }
+            Self::Precision(path, found, expected) => write!(
+                f,
+                "{}: precision {found} does not match inheriting precision {expected}",
+                path.display()
+            ),
+        }
+    }
+}
+
+/// Canonical path of the file that first defined a tile, and the tile's index within that file,
+/// which tiles replacing it by name keep
+type Origin = (PathBuf, usize);
+
+/// A tile file merged with every file that it extends or includes
+#[derive(Clone, Debug, Default, Hash, PartialEq, Eq)]
+pub struct Inherited {
+    /// The merged file, which no longer extends or includes any files
+    file: RawFile,
+    /// Every loaded file that was created by an older version of the format, and its version
+    outdated: Vec<(PathBuf, usize)>,
+}
+
+impl Inherited {
+    /// Loads the file at the provided path, merging in the tiles of every file it extends or
+    /// includes.
+    ///
+    /// Inherited tiles start with the base file's tiles, followed by each included file's tiles.
+    /// Named tiles are then removed, reweighted, or replaced by tiles with the same name, and any
+    /// remaining tiles are added to the end. The merged file keeps the identifier and precision of
+    /// the file at the provided path, and every inherited file must have the same precision.
+    ///
+    /// Files that are inherited more than once, such as a base file shared by two included files,
+    /// only add their tiles the first time.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let mut inherited = Self::default();
+        let (mut file, tiles) = inherited.resolve(path.as_ref(), &mut vec![])?;
+
+        file.tiles = tiles.into_iter().map(|(tile, _)| tile).collect();
+        inherited.file = file;
+        Ok(inherited)
+    }
+
+    /// Returns a reference to the merged file
+    pub const fn file(&self) -> &RawFile {
+        &self.file
+    }
+    /// Returns the merged file
+    pub fn into_file(self) -> RawFile {
+        self.file
+    }
+    /// Returns every loaded file that was created by an older version of the format, and its
+    /// version
+    pub fn outdated(&self) -> &[(PathBuf, usize)] {
+        &self.outdated
+    }
+
+    /// Loads and merges the file at the provided path, where `stack` contains every file that is
+    /// currently being merged.
+    ///
+    /// The merged tiles are returned separately from the file, along with where each was read from.
+    fn resolve(
+        &mut self,
+        path: &Path,
+        stack: &mut Vec<PathBuf>,
+    ) -> Result<(RawFile, Vec<(RawTile, Origin)>), Error> {
+        let load = |error| Error::Load(path.to_path_buf(), error);
+        let canonical =
+            fs::canonicalize(path).map_err(|e| load(migration::Error::Io(e.to_string())))?;
+
+        if let Some(start) = stack.iter().position(|p| *p == canonical) {
+            let mut cycle = stack[start..].to_vec();
+
+            cycle.push(canonical);
+            return Err(Error::Cycle(cycle));
+        }
+
+        let (migration, _) = Migration::read(path).map_err(load)?;
+
+        let known = self
+            .outdated
+            .iter()
+            .any(|(p, _)| fs::canonicalize(p).is_ok_and(|p| p == canonical));
+
+        if migration.is_upgraded() && !known {
+            self.outdated.push((path.to_path_buf(), migration.from()));
+        }
+
+        let mut file = migration.into_file();
+        let directory = path.parent().unwrap_or_else(|| Path::new(""));
+        let mut tiles: Vec<(RawTile, Origin)> = vec![];
+
+        stack.push(canonical.clone());
+
+        for parent in file.extends.iter().chain(&file.include) {
+            let parent = directory.join(parent);
+            let (inherited, inherited_tiles) = self.resolve(&parent, stack)?;
+
+            if inherited.precision != file.precision {
+                return Err(Error::Precision(
+                    parent,
+                    inherited.precision,
+                    file.precision,
+                ));
+            }
+
+            for (tile, origin) in inherited_tiles {
+                if tiles.iter().all(|(_, o)| *o != origin) {
+                    tiles.push((tile, origin));
+                }
+            }
+        }
+
+        stack.pop();
+
+        let unknown = |name: &String| Error::UnknownTile(path.to_path_buf(), name.clone());
+        let names = file
+            .remove
+            .iter()
+            .chain(file.weights.iter().map(|(name, _)| name));
+
+        if names.into_iter().any(String::is_empty) {
+            return Err(Error::EmptyName(path.to_path_buf()));
+        }
+
+        for name in &file.remove {
+            let count = tiles.len();
+
+            tiles.retain(|(t, _)| t.name != *name);
+
+            if tiles.len() == count {
+                return Err(unknown(name));
+            }
+        }
+        for (name, weight) in &file.weights {
+            let mut found = false;
+
+            for (tile, _) in tiles.iter_mut().filter(|(t, _)| t.name == *name) {
+                tile.weight = *weight;
+                found = true;
+            }
+
+            if !found {
+                return Err(unknown(name));
+            }
+        }
+        for (index, tile) in file.tiles.drain(..).enumerate() {
+            match tiles
+                .iter_mut()
+                .find(|(t, _)| !tile.name.is_empty() && t.name == tile.name)
+            {
+                // Replaced tiles keep their origin, so that the replaced tile is not merged back in
+                // when its file is inherited again.
+                Some((inherited, _)) => *inherited = tile,
+                None => tiles.push((tile, (canonical.clone(), index))),
+            }
+        }
+
+        file.extends = None;
+        file.include.clear();
+        file.remove.clear();
+        file.weights.clear();
+
+        Ok((file, tiles))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns an empty directory for the given test's files
+    fn directory(test: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("inheritance-{}-{test}", std::process::id()));
+
+        if path.exists() {
+            fs::remove_dir_all(&path).unwrap();
+        }
+
+        fs::create_dir_all(&path).unwrap();
+        path
+    }
+
+    /// Writes a version `1` file with the given inheritance fields and named tiles of weight `1`,
+    /// returning its path.
+    ///
+    /// Inheritance fields that are left out are read as empty.
+    fn write(directory: &Path, name: &str, fields: &str, tiles: &[&str]) -> PathBuf {
+        let tiles: Vec<String> = tiles
+            .iter()
+            .map(|name| {
+                format!(
+                    "(name: {name:?}, source: \"\", layer: 0, weight: 1, \
+                     nodes: ([0], [0], [0], [0]), interior: [])"
+                )
+            })
+            .collect();
+        let path = directory.join(name);
+        let contents = format!(
+            "(id: 0, version: 1, precision: 1, {fields} tiles: [{}])",
+            tiles.join(", ")
+        );
+
+        fs::write(&path, contents).unwrap();
+        path
+    }
+
+    /// Returns the name and weight of each tile of the provided file
+    fn tiles(file: &RawFile) -> Vec<(&str, i32)> {
+        file.tiles
+            .iter()
+            .map(|t| (t.name.as_str(), t.weight))
+            .collect()
+    }
+
+    #[test]
+    fn inherited_tiles_are_merged_in_order() {
+        let directory = directory("merged");
+
+        write(&directory, "base.ron", "", &["a", "b", "c"]);
+        write(&directory, "extra.ron", "", &["d"]);
+
+        let path = write(
+            &directory,
+            "file.ron",
+            "extends: Some(\"base.ron\"), include: [\"extra.ron\"], remove: [\"b\"], \
+             weights: [(\"c\", 5)],",
+            &["a", "e"],
+        );
+        let inherited = Inherited::load(path).unwrap();
+
+        assert_eq!(
+            tiles(inherited.file()),
+            [("a", 1), ("c", 5), ("d", 1), ("e", 1)]
+        );
+        assert_eq!(inherited.file().extends, None);
+        assert!(inherited.file().include.is_empty());
+        assert_eq!(inherited.outdated().len(), 3);
+        assert!(inherited
+            .outdated()
+            .iter()
+            .all(|(_, version)| *version == 1));
+    }
+
+    #[test]
+    fn shared_files_are_only_merged_once() {
+        let directory = directory("shared");
+
+        write(&directory, "base.ron", "", &["a", "b"]);
+        write(
+            &directory,
+            "left.ron",
+            "extends: Some(\"base.ron\"),",
+            &["a", "c"],
+        );
+        write(
+            &directory,
+            "right.ron",
+            "extends: Some(\"./base.ron\"),",
+            &["d"],
+        );
+
+        let path = write(
+            &directory,
+            "file.ron",
+            "include: [\"left.ron\", \"right.ron\"],",
+            &[],
+        );
+        let inherited = Inherited::load(path).unwrap();
+
+        assert_eq!(
+            tiles(inherited.file()),
+            [("a", 1), ("b", 1), ("c", 1), ("d", 1)]
+        );
+        assert_eq!(inherited.outdated().len(), 4);
+    }
+
+    #[test]
+    fn different_precisions_are_an_error() {
+        let directory = directory("precision");
+        let base = write(&directory, "base.ron", "", &["a"]);
+
+        fs::write(
+            &base,
+            fs::read_to_string(&base)
+                .unwrap()
+                .replace("precision: 1", "precision: 2"),
+        )
+        .unwrap();
+
+        let path = write(&directory, "file.ron", "extends: Some(\"base.ron\"),", &[]);
+
+        assert_eq!(
+            Inherited::load(path),
+            Err(Error::Precision(directory.join("base.ron"), 2, 1))
+        );
+    }
+
+    #[test]
+    fn unknown_tiles_are_an_error() {
+        let directory = directory("unknown");
+
+        write(&directory, "base.ron", "", &["a"]);
+
+        let path = write(
+            &directory,
+            "file.ron",
+            "extends: Some(\"base.ron\"), weights: [(\"b\", 2)],",
+            &[],
+        );
+
+        assert_eq!(
+            Inherited::load(&path),
+            Err(Error::UnknownTile(path, "b".to_string()))
+        );
+    }
+
+    #[test]
+    fn empty_names_are_an_error() {
+        let directory = directory("empty");
+
+        write(&directory, "base.ron", "", &["", "a"]);
+
+        for fields in ["remove: [\"\"],", "weights: [(\"\", 2)],"] {
+            let fields = format!("extends: Some(\"base.ron\"), {fields}");
+            let path = write(&directory, "file.ron", &fields, &[]);
+
+            assert_eq!(Inherited::load(&path), Err(Error::EmptyName(path)));
+        }
+    }
+
+    #[test]
+    fn files_including_each_other_are_an_error() {
+        let directory = directory("cycle");
+        let first = write(&directory, "first.ron", "include: [\"second.ron\"],", &[]);
+        let second = write(
+            &directory,
+            "second.ron",
+            "extends: Some(\"first.ron\"),",
+            &[],
+        );
+        let first = fs::canonicalize(first).unwrap();
+        let second = fs::canonicalize(second).unwrap();
+
+        assert_eq!(
+            Inherited::load(&first),
+            Err(Error::Cycle(vec![first.clone(), second, first]))
+        );
+    }
+
+    #[test]
+    fn files_including_themselves_are_an_error() {
+        let directory = directory("self");
+        let path = write(&directory, "file.ron", "include: [\"./file.ron\"],", &[]);
+        let path = fs::canonicalize(path).unwrap();
+
+        assert_eq!(
+            Inherited::load(&path),
+            Err(Error::Cycle(vec![path.clone(), path]))
+        );
+    }
+
+    #[test]
+    fn missing_files_are_an_error() {
+        let directory = directory("missing");
+        let path = write(&directory, "file.ron", "include: [\"other.ron\"],", &[]);
+
+        assert!(matches!(
+            Inherited::load(path),
+            Err(Error::Load(path, migration::Error::Io(_))) if path.ends_with("other.ron")
+        ));
+    }
+}
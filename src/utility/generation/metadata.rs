@@ -0,0 +1,320 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Display,
+    hash::{Hash, Hasher},
+};
+
+use serde::{
+    de::{self, EnumAccess, VariantAccess, Visitor},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+
+use crate::collections::grid::{vec::VecGrid, Grid, Idx};
+
+use super::tile::{RawTile, Tile};
+
+/// Typed value of a tile's property.
+///
+/// Properties are stored as plain values in text formats, such as `3` or `"grass"`, where their
+/// type is told apart by the value itself. Binary formats can not tell values apart, so they also
+/// store the type of each property.
+#[derive(Clone, Debug)]
+pub enum Property {
+    /// A boolean, such as whether a tile blocks light
+    Bool(bool),
+    /// An integer, such as a movement cost
+    Int(i64),
+    /// A floating point number, such as a light level
+    Float(f64),
+    /// A string, such as the name of a sound
+    String(String),
+}
+
+impl Property {
+    /// Returns the value if it is a boolean
+    pub const fn as_bool(&self) -> Option<bool> {
+        match self {
+            Self::Bool(value) => Some(*value),
+            _ => None,
+        }
+    }
+    /// Returns the value if it is an integer
+    pub const fn as_int(&self) -> Option<i64> {
+        match self {
+            Self::Int(value) => Some(*value),
+            _ => None,
+        }
+    }
+    /// Returns the value if it is a number, converting integers to floating point numbers
+    pub const fn as_float(&self) -> Option<f64> {
+        match self {
+            Self::Int(value) => Some(*value as f64),
+            Self::Float(value) => Some(*value),
+            _ => None,
+        }
+    }
+    /// Returns the value if it is a string
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::String(value) => Some(value),
+            _ => None,
+        }
+    }
+}
+
+impl Display for Property {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Bool(value) => write!(f, "{value}"),
+            Self::Int(value) => write!(f, "{value}"),
+            Self::Float(value) => write!(f, "{value}"),
+            Self::String(value) => write!(f, "{value:?}"),
+        }
+    }
+}
+
+/// Name of each type of property, in the order they are stored by binary formats
+const TYPES: [&str; 4] = ["Bool", "Int", "Float", "String"];
+
+impl Serialize for Property {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let human = serializer.is_human_readable();
+
+        match self {
+            Self::Bool(value) if human => value.serialize(serializer),
+            Self::Int(value) if human => value.serialize(serializer),
+            Self::Float(value) if human => value.serialize(serializer),
+            Self::String(value) if human => value.serialize(serializer),
+            Self::Bool(value) => {
+                serializer.serialize_newtype_variant("Property", 0, TYPES[0], value)
+            }
+            Self::Int(value) => {
+                serializer.serialize_newtype_variant("Property", 1, TYPES[1], value)
+            }
+            Self::Float(value) => {
+                serializer.serialize_newtype_variant("Property", 2, TYPES[2], value)
+            }
+            Self::String(value) => {
+                serializer.serialize_newtype_variant("Property", 3, TYPES[3], value)
+            }
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Property {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        if deserializer.is_human_readable() {
+            deserializer.deserialize_any(PropertyVisitor)
+        } else {
+            deserializer.deserialize_enum("Property", &TYPES, PropertyVisitor)
+        }
+    }
+}
+
+/// Reads properties either from plain values, or from values stored along with their type
+struct PropertyVisitor;
+
+impl<'de> Visitor<'de> for PropertyVisitor {
+    type Value = Property;
+
+    fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "a boolean, integer, floating point number, or string")
+    }
+
+    fn visit_bool<E: de::Error>(self, value: bool) -> Result<Self::Value, E> {
+        Ok(Property::Bool(value))
+    }
+    fn visit_i64<E: de::Error>(self, value: i64) -> Result<Self::Value, E> {
+        Ok(Property::Int(value))
+    }
+    fn visit_u64<E: de::Error>(self, value: u64) -> Result<Self::Value, E> {
+        i64::try_from(value)
+            .map(Property::Int)
+            .map_err(|_| E::custom(format!("integer {value} is too large")))
+    }
+    fn visit_f64<E: de::Error>(self, value: f64) -> Result<Self::Value, E> {
+        Ok(Property::Float(value))
+    }
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<Self::Value, E> {
+        Ok(Property::String(value.to_string()))
+    }
+    fn visit_string<E: de::Error>(self, value: String) -> Result<Self::Value, E> {
+        Ok(Property::String(value))
+    }
+    fn visit_enum<A: EnumAccess<'de>>(self, data: A) -> Result<Self::Value, A::Error> {
+        let (index, value): (u32, _) = data.variant()?;
+
+        match index {
+            0 => value.newtype_variant().map(Property::Bool),
+            1 => value.newtype_variant().map(Property::Int),
+            2 => value.newtype_variant().map(Property::Float),
+            3 => value.newtype_variant().map(Property::String),
+            _ => Err(de::Error::invalid_value(
+                de::Unexpected::Unsigned(u64::from(index)),
+                &"a property type from 0 to 3",
+            )),
+        }
+    }
+}
+
+// Floating point numbers are compared by their bits, so that raw tiles can still be compared and
+// hashed.
+impl PartialEq for Property {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Bool(a), Self::Bool(b)) => a == b,
+            (Self::Int(a), Self::Int(b)) => a == b,
+            (Self::Float(a), Self::Float(b)) => a.to_bits() == b.to_bits(),
+            (Self::String(a), Self::String(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+impl Eq for Property {}
+
+impl Hash for Property {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        std::mem::discriminant(self).hash(state);
+
+        match self {
+            Self::Bool(value) => value.hash(state),
+            Self::Int(value) => value.hash(state),
+            Self::Float(value) => value.to_bits().hash(state),
+            Self::String(value) => value.hash(state),
+        }
+    }
+}
+
+/// Tags and properties of raw tiles, looked up from the tiles of generated maps
+#[derive(Clone, Debug, Default, Hash, PartialEq, Eq)]
+pub struct Metadata {
+    /// Tags of each raw tile, indexed by raw tile
+    tags: Vec<Vec<String>>,
+    /// Properties of each raw tile, indexed by raw tile
+    properties: Vec<BTreeMap<String, Property>>,
+}
+
+impl Metadata {
+    /// Creates new metadata from the provided raw tiles, in the same order that they were added to
+    /// their tile set
+    pub fn new(raws: &[RawTile]) -> Self {
+        Self {
+            tags: raws.iter().map(|r| r.tags.clone()).collect(),
+            properties: raws.iter().map(|r| r.properties.clone()).collect(),
+        }
+    }
+
+    /// Returns the tags of the provided tile
+    pub fn tags<const P: usize>(&self, tile: &Tile<P>) -> &[String] {
+        self.tags.get(tile.index()).map_or(&[], Vec::as_slice)
+    }
+    /// Returns `true` if the provided tile has the given tag
+    pub fn has_tag<const P: usize>(&self, tile: &Tile<P>, tag: &str) -> bool {
+        self.tags(tile).iter().any(|t| t == tag)
+    }
+    /// Returns the value of the provided tile's property with the given name, if any
+    pub fn property<const P: usize>(&self, tile: &Tile<P>, name: &str) -> Option<&Property> {
+        self.properties.get(tile.index())?.get(name)
+    }
+
+    /// Returns `true` if the cell at the given index of the provided map contains a tile with the
+    /// given tag, where empty cells have no tags
+    pub fn has_tag_at<const P: usize>(
+        &self,
+        map: &VecGrid<Tile<P>>,
+        index: Idx,
+        tag: &str,
+    ) -> bool {
+        map.get(index).is_some_and(|t| self.has_tag(t, tag))
+    }
+    /// Returns the value of the property with the given name of the tile within the cell at the
+    /// given index of the provided map, if any
+    pub fn property_at<const P: usize>(
+        &self,
+        map: &VecGrid<Tile<P>>,
+        index: Idx,
+        name: &str,
+    ) -> Option<&Property> {
+        self.property(map.get(index)?, name)
+    }
+    /// Returns the index of every cell of the provided map containing a tile with the given tag
+    pub fn cells_with_tag<const P: usize>(&self, map: &VecGrid<Tile<P>>, tag: &str) -> Vec<Idx> {
+        map.indexes()
+            .into_iter()
+            .filter(|i| self.has_tag_at(map, *i, tag))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utility::generation::fixtures::filled;
+
+    /// Returns a walkable grass tile and a water tile with a movement cost
+    fn raws() -> [RawTile; 2] {
+        [
+            RawTile {
+                name: "grass".to_string(),
+                tags: vec!["walkable".to_string()],
+                ..filled::<1>([0; 4])
+            },
+            RawTile {
+                name: "water".to_string(),
+                tags: vec!["water".to_string()],
+                properties: [("cost".to_string(), Property::Int(3))].into(),
+                ..filled::<1>([0; 4])
+            },
+        ]
+    }
+
+    #[test]
+    fn tiles_are_looked_up_by_index() {
+        let raws = raws();
+        let metadata = Metadata::new(&raws);
+        let grass = Tile::<1>::from_raw(0, &raws[0]).unwrap();
+        let water = Tile::<1>::from_raw(1, &raws[1]).unwrap();
+
+        assert!(metadata.has_tag(&grass, "walkable"));
+        assert!(!metadata.has_tag(&water, "walkable"));
+        assert_eq!(metadata.property(&water, "cost"), Some(&Property::Int(3)));
+        assert_eq!(metadata.property(&grass, "cost"), None);
+
+        // Tiles that were not added to the tile set have no metadata.
+        let unknown = Tile::<1>::from_raw(2, &raws[0]).unwrap();
+
+        assert!(metadata.tags(&unknown).is_empty());
+    }
+
+    #[test]
+    fn cells_are_queried_from_maps() {
+        let raws = raws();
+        let metadata = Metadata::new(&raws);
+        let mut map = VecGrid::new(3, 1);
+
+        map.insert((0, 0), Tile::<1>::from_raw(0, &raws[0]).unwrap());
+        map.insert((1, 0), Tile::<1>::from_raw(1, &raws[1]).unwrap());
+
+        assert!(metadata.has_tag_at(&map, (0, 0), "walkable"));
+        assert!(!metadata.has_tag_at(&map, (2, 0), "walkable"));
+        assert_eq!(
+            metadata.property_at(&map, (1, 0), "cost"),
+            Some(&Property::Int(3))
+        );
+        assert_eq!(metadata.property_at(&map, (2, 0), "cost"), None);
+        assert_eq!(metadata.cells_with_tag(&map, "water"), vec![(1, 0)]);
+    }
+
+    #[test]
+    fn properties_are_plain_values_in_text() {
+        let properties: BTreeMap<String, Property> =
+            ron::from_str(r#"{"cost": 3, "speed": 1.5, "lit": true, "sound": "splash"}"#).unwrap();
+
+        assert_eq!(properties["cost"], Property::Int(3));
+        assert_eq!(properties["speed"], Property::Float(1.5));
+        assert_eq!(properties["lit"], Property::Bool(true));
+        assert_eq!(properties["sound"], Property::String("splash".to_string()));
+        assert_eq!(serde_json::to_string(&Property::Int(-4)).unwrap(), "-4");
+    }
+}
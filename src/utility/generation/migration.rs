@@ -0,0 +1,277 @@
+use std::{fmt::Display, fs, path::Path};
+
+use ron::{Map, Value};
+
+use super::{
+    format::{self, Format},
+    tile::RawFile,
+};
+
+/// Version of the tile file format that files are upgraded to.
+///
+/// Each version has only added fields to the format, which are read as empty when missing:
+///
+/// 1. Interior patterns of tiles
+/// 2. Inheritance from other files, and names of tiles
+/// 3. Tags and properties of tiles
+pub const CURRENT_VERSION: usize = 3;
+
+/// Error that may occur while loading or upgrading a tile file
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub enum Error {
+    /// The file could not be read or written, stored as the reason
+    Io(String),
+    /// The file could not be encoded or decoded
+    Format(format::Error),
+    /// The file's version could not be read, stored as the reason
+    Parse(String),
+    /// The file does not contain a structure at its root
+    NotAFile,
+    /// The file was created by a newer version of the format
+    Unsupported(usize),
+    /// The file is a binary file created by a different version of the format
+    Binary(usize),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(reason) => write!(f, "could not access file: {reason}"),
+            Self::Format(error) => write!(f, "{error}"),
+            Self::Parse(reason) => write!(f, "could not parse version: {reason}"),
+            Self::NotAFile => write!(f, "file does not contain a tile set"),
+            Self::Unsupported(version) => write!(
+                f,
+                "file version {version} is newer than supported version {CURRENT_VERSION}"
+            ),
+            Self::Binary(version) => write!(
+                f,
+                "binary file version {version} can not be upgraded, convert it from a text file instead"
+            ),
+        }
+    }
+}
+
+/// A tile file that has been upgraded to the current version of the format
+#[derive(Clone, Debug, Default, Hash, PartialEq, Eq)]
+pub struct Migration {
+    /// The upgraded file
+    file: RawFile,
+    /// Version of the file before it was upgraded
+    from: usize,
+}
+
+impl Migration {
+    /// Parses the provided file contents in the given format, upgrading them to the current version
+    /// of the format.
+    ///
+    /// Files without a version are treated as version `0`. Since every version has only added
+    /// fields, older text files are read the same way as current files, with any missing fields
+    /// left empty. Binary files store every field by position, so they can not be upgraded, and
+    /// must be converted again from a text format instead.
+    pub fn decode(bytes: &[u8], format: Format) -> Result<Self, Error> {
+        if format == Format::Binary {
+            // The version always follows the identifier, so it can be read without the rest.
+            let (_, from): (usize, usize) = format.decode(bytes).map_err(Error::Format)?;
+
+            if from != CURRENT_VERSION {
+                return Err(Error::Binary(from));
+            }
+
+            let file = format.decode(bytes).map_err(Error::Format)?;
+
+            return Ok(Self { file, from });
+        }
+
+        let Value::Map(map) = format.decode(bytes).map_err(Error::Format)? else {
+            return Err(Error::NotAFile);
+        };
+
+        let from = match field(&map, "version") {
+            Some(value) => value
+                .clone()
+                .into_rust()
+                .map_err(|e| Error::Parse(e.to_string()))?,
+            None => 0,
+        };
+
+        if from > CURRENT_VERSION {
+            return Err(Error::Unsupported(from));
+        }
+
+        let mut file: RawFile = format.decode(bytes).map_err(Error::Format)?;
+
+        file.version = CURRENT_VERSION;
+        Ok(Self { file, from })
+    }
+    /// Reads the file at the provided path, detecting its format, and upgrades it to the current
+    /// version of the format
+    pub fn read(path: impl AsRef<Path>) -> Result<(Self, Format), Error> {
+        let bytes = fs::read(&path).map_err(|e| Error::Io(e.to_string()))?;
+        let format = Format::of(&path, &bytes);
+
+        Ok((Self::decode(&bytes, format)?, format))
+    }
+    /// Upgrades the file at the provided path and writes it back in the same format, returning the
+    /// version it was upgraded from.
+    ///
+    /// Files that are already up to date are left untouched.
+    pub fn rewrite(path: impl AsRef<Path>) -> Result<usize, Error> {
+        let (migration, format) = Self::read(&path)?;
+
+        if migration.is_upgraded() {
+            let bytes = format.encode(&migration.file).map_err(Error::Format)?;
+
+            fs::write(&path, bytes).map_err(|e| Error::Io(e.to_string()))?;
+        }
+
+        Ok(migration.from)
+    }
+
+    /// Returns a reference to the upgraded file
+    pub const fn file(&self) -> &RawFile {
+        &self.file
+    }
+    /// Returns the upgraded file
+    pub fn into_file(self) -> RawFile {
+        self.file
+    }
+    /// Returns the version of the file before it was upgraded
+    pub const fn from(&self) -> usize {
+        self.from
+    }
+    /// Returns `true` if the file was created by an older version of the format
+    pub const fn is_upgraded(&self) -> bool {
+        self.from < CURRENT_VERSION
+    }
+}
+
+/// Returns a reference to the value of the field with the given name, if any
+fn field<'a>(map: &'a Map, name: &str) -> Option<&'a Value> {
+    map.iter()
+        .find(|(key, _)| matches!(key, Value::String(key) if key == name))
+        .map(|(_, value)| value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A version `0` file, before tiles had interior patterns, names, tags, or properties
+    const VERSION_0: &str = "(
+        id: 2,
+        precision: 1,
+        tiles: [
+            (source: \"grass.png\", layer: 0, weight: 3, nodes: ([1], [1], [1], [1])),
+        ],
+    )";
+
+    #[test]
+    fn old_files_are_upgraded() {
+        let migration = Migration::decode(VERSION_0.as_bytes(), Format::Ron).unwrap();
+        let file = migration.file();
+        let tile = &file.tiles[0];
+
+        assert_eq!(migration.from(), 0);
+        assert!(migration.is_upgraded());
+        assert_eq!(file.version, CURRENT_VERSION);
+        assert_eq!((file.id, file.precision), (2, 1));
+        assert_eq!(file.extends, None);
+        assert!(file.include.is_empty() && file.remove.is_empty() && file.weights.is_empty());
+        assert_eq!((tile.source.as_str(), tile.weight), ("grass.png", 3));
+        assert!(tile.interior.is_empty() && tile.name.is_empty() && tile.tags.is_empty());
+        assert!(tile.properties.is_empty());
+    }
+
+    #[test]
+    fn upgraded_files_decode_like_current_files() {
+        let old = Migration::decode(VERSION_0.as_bytes(), Format::Ron).unwrap();
+        let current = Format::Ron.encode(old.file()).unwrap();
+        let migration = Migration::decode(&current, Format::Ron).unwrap();
+
+        assert_eq!(migration.from(), CURRENT_VERSION);
+        assert!(!migration.is_upgraded());
+        assert_eq!(migration.file(), old.file());
+    }
+
+    #[test]
+    fn json_files_are_upgraded() {
+        let json = r#"{"id": 1, "version": 1, "precision": 1, "tiles": [
+            {"source": "", "layer": 0, "weight": 1, "nodes": [[0], [0], [0], [0]], "interior": []}
+        ]}"#;
+        let migration = Migration::decode(json.as_bytes(), Format::Json).unwrap();
+
+        assert_eq!(migration.from(), 1);
+        assert_eq!(migration.file().extends, None);
+        assert_eq!(migration.file().tiles.len(), 1);
+    }
+
+    #[test]
+    fn newer_files_are_unsupported() {
+        let newer = format!("(id: 0, version: {}, tiles: [])", CURRENT_VERSION + 1);
+
+        assert_eq!(
+            Migration::decode(newer.as_bytes(), Format::Ron),
+            Err(Error::Unsupported(CURRENT_VERSION + 1))
+        );
+    }
+
+    #[test]
+    fn malformed_files_are_errors() {
+        assert_eq!(
+            Migration::decode(b"[1, 2]", Format::Ron),
+            Err(Error::NotAFile)
+        );
+        assert!(matches!(
+            Migration::decode(b"(version: 0, tiles: [(layer: 0), 4])", Format::Ron),
+            Err(Error::Format(_))
+        ));
+        assert!(matches!(
+            Migration::decode(b"(version: \"one\")", Format::Ron),
+            Err(Error::Parse(_))
+        ));
+        assert!(matches!(
+            Migration::decode(b"(", Format::Ron),
+            Err(Error::Format(_))
+        ));
+    }
+
+    #[test]
+    fn outdated_binary_files_are_errors() {
+        let mut file = Migration::decode(VERSION_0.as_bytes(), Format::Ron)
+            .unwrap()
+            .into_file();
+        let current = Format::Binary.encode(&file).unwrap();
+
+        file.version = 1;
+
+        let outdated = Format::Binary.encode(&file).unwrap();
+
+        assert_eq!(
+            Migration::decode(&current, Format::Binary).unwrap().from(),
+            3
+        );
+        assert_eq!(
+            Migration::decode(&outdated, Format::Binary),
+            Err(Error::Binary(1))
+        );
+    }
+
+    #[test]
+    fn rewriting_only_changes_outdated_files() {
+        let path = std::env::temp_dir().join(format!("migration-{}.ron", std::process::id()));
+
+        fs::write(&path, VERSION_0).unwrap();
+
+        assert_eq!(Migration::rewrite(&path), Ok(0));
+
+        let upgraded = fs::read(&path).unwrap();
+
+        assert_eq!(Migration::rewrite(&path), Ok(CURRENT_VERSION));
+        assert_eq!(fs::read(&path).unwrap(), upgraded);
+
+        fs::remove_file(&path).unwrap();
+
+        assert!(matches!(Migration::rewrite(&path), Err(Error::Io(_))));
+    }
+}
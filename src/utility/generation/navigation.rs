@@ -0,0 +1,351 @@
+use bevy::utils::{HashMap, HashSet};
+
+use crate::collections::grid::{vec::VecGrid, Grid, Idx};
+
+use super::{constraint::Traversal, tile::Tile, Side};
+
+/// Describes how a point within a navigation graph is connected
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Kind {
+    /// The point is not connected to any other cell
+    Isolated,
+    /// The point is connected to a single other cell
+    DeadEnd,
+    /// The point is connected to three or more other cells
+    Junction,
+    /// The point is connected to two other cells, and was only added to break up a loop
+    Waypoint,
+}
+
+/// A cell where corridors start or end
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub struct Point {
+    /// Index of the point's cell
+    index: Idx,
+    /// How the point is connected
+    kind: Kind,
+}
+
+impl Point {
+    /// Returns the index of the point's cell
+    pub const fn index(&self) -> Idx {
+        self.index
+    }
+    /// Returns how the point is connected
+    pub const fn kind(&self) -> Kind {
+        self.kind
+    }
+}
+
+/// An unbranching route between two points
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub struct Corridor {
+    /// Index of the point that the corridor starts at
+    start: usize,
+    /// Index of the point that the corridor ends at
+    end: usize,
+    /// Index of each cell between the corridor's points, in order from the start
+    cells: Vec<Idx>,
+}
+
+impl Corridor {
+    /// Returns the index of the point that the corridor starts at
+    pub const fn start(&self) -> usize {
+        self.start
+    }
+    /// Returns the index of the point that the corridor ends at
+    pub const fn end(&self) -> usize {
+        self.end
+    }
+    /// Returns the index of each cell between the corridor's points, in order from the start
+    pub fn cells(&self) -> &[Idx] {
+        &self.cells
+    }
+    /// Returns the number of steps needed to travel from one end of the corridor to the other
+    pub fn length(&self) -> usize {
+        self.cells.len() + 1
+    }
+}
+
+/// Graph of the routes through a generated map, made up of points connected by corridors.
+///
+/// Two neighbouring cells are connected if the sides facing each other may both be travelled
+/// through. Cells connected to exactly two other cells are part of a corridor, while every other
+/// connected cell is a point. Loops without any points are broken up by a single waypoint.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Navigation {
+    /// Every point within the graph
+    points: Vec<Point>,
+    /// Every corridor within the graph
+    corridors: Vec<Corridor>,
+    /// Index of the point within each point's cell
+    lookup: HashMap<Idx, usize>,
+}
+
+impl Navigation {
+    /// Creates a new navigation graph of the provided map, where `tiles` are the tiles that the map
+    /// was generated from.
+    ///
+    /// If `wrap` is `true`, cells on opposite edges of the map are treated as neighbours.
+    pub fn new<const P: usize>(
+        tiles: &[Tile<P>],
+        map: &VecGrid<Tile<P>>,
+        traversal: &Traversal,
+        wrap: bool,
+    ) -> Self {
+        let size = map.size();
+        let mut openness = HashMap::new();
+
+        // Which sides of each tile are open, keyed by the tile, where the first of any equal tiles
+        // is used.
+        for (index, tile) in tiles.iter().enumerate() {
+            openness.entry(tile).or_insert_with(|| {
+                [Side::Top, Side::Left, Side::Right, Side::Bottom]
+                    .map(|side| traversal.is_open(tiles, index, side))
+            });
+        }
+
+        let is_open = |index: Idx, side: Side| {
+            map.get(index)
+                .and_then(|tile| openness.get(tile))
+                .is_some_and(|open| open[usize::from(side)])
+        };
+
+        // Every cell with an open side, along with the neighbours it is connected to.
+        let mut links = HashMap::new();
+
+        for index in map.indexes() {
+            if !Side::Top.into_iter().any(|side| is_open(index, side)) {
+                continue;
+            }
+
+            let connected: Vec<(Side, Idx)> = Side::Top
+                .into_iter()
+                .filter(|side| is_open(index, *side))
+                .filter_map(|side| Some((side, side.neighbor(index, size, wrap)?)))
+                .filter(|(side, neighbor)| is_open(*neighbor, side.opposite()))
+                .collect();
+
+            links.insert(index, connected);
+        }
+
+        let mut navigation = Self::default();
+        let mut cells: Vec<Idx> = links.keys().copied().collect();
+
+        cells.sort_unstable_by_key(|(x, y)| (*y, *x));
+
+        for index in &cells {
+            let kind = match links[index].len() {
+                0 => Kind::Isolated,
+                1 => Kind::DeadEnd,
+                2 => continue,
+                _ => Kind::Junction,
+            };
+
+            navigation.add_point(*index, kind);
+        }
+
+        let mut walked = HashSet::new();
+        let mut visited = HashSet::new();
+
+        for point in 0..navigation.points.len() {
+            navigation.walk_from(point, &links, &mut walked, &mut visited);
+        }
+
+        // Any remaining corridor cells form loops that never reach a point.
+        for index in cells {
+            if links[&index].len() == 2 && !visited.contains(&index) {
+                let point = navigation.add_point(index, Kind::Waypoint);
+
+                visited.insert(index);
+                navigation.walk_from(point, &links, &mut walked, &mut visited);
+            }
+        }
+
+        navigation
+    }
+
+    /// Returns every point within the graph
+    pub fn points(&self) -> &[Point] {
+        &self.points
+    }
+    /// Returns every corridor within the graph
+    pub fn corridors(&self) -> &[Corridor] {
+        &self.corridors
+    }
+    /// Returns the index of the point within the provided cell, if any
+    pub fn point_at(&self, index: Idx) -> Option<usize> {
+        self.lookup.get(&index).copied()
+    }
+    /// Returns every corridor that starts or ends at the given point
+    pub fn corridors_of(&self, point: usize) -> impl Iterator<Item = &Corridor> {
+        self.corridors
+            .iter()
+            .filter(move |c| c.start == point || c.end == point)
+    }
+
+    /// Adds a new point to the graph, returning its index
+    fn add_point(&mut self, index: Idx, kind: Kind) -> usize {
+        self.points.push(Point { index, kind });
+        self.lookup.insert(index, self.points.len() - 1);
+        self.points.len() - 1
+    }
+    /// Follows each side of the given point that has not already been walked to the next point,
+    /// adding a corridor for each
+    fn walk_from(
+        &mut self,
+        start: usize,
+        links: &HashMap<Idx, Vec<(Side, Idx)>>,
+        walked: &mut HashSet<(Idx, Side)>,
+        visited: &mut HashSet<Idx>,
+    ) {
+        let origin = self.points[start].index;
+
+        for &(mut side, mut current) in &links[&origin] {
+            if !walked.insert((origin, side)) {
+                continue;
+            }
+
+            let mut cells = vec![];
+
+            // Corridor cells have exactly two links, so the next one is the one not arrived through.
+            while self.point_at(current).is_none() {
+                let Some(&(next_side, next)) =
+                    links[&current].iter().find(|(s, _)| *s != side.opposite())
+                else {
+                    break;
+                };
+
+                visited.insert(current);
+                cells.push(current);
+                side = next_side;
+                current = next;
+            }
+
+            walked.insert((current, side.opposite()));
+
+            if let Some(end) = self.point_at(current) {
+                self.corridors.push(Corridor { start, end, cells });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utility::generation::fixtures::{openings, tile};
+
+    /// Traversal through sides containing an open node
+    const OPEN: Traversal = Traversal::Node(1);
+
+    /// Returns a map of the provided tiles, given as rows of tile indexes
+    fn map(tiles: &[Tile<1>], rows: &[&[usize]]) -> VecGrid<Tile<1>> {
+        let mut map = VecGrid::new(rows[0].len(), rows.len());
+
+        for (y, row) in rows.iter().enumerate() {
+            for (x, tile) in row.iter().enumerate() {
+                map.insert((x, y), tiles[*tile]);
+            }
+        }
+
+        map
+    }
+
+    /// Open on the top, left, right, and bottom side respectively
+    const T: usize = 1;
+    const L: usize = 2;
+    const R: usize = 4;
+    const B: usize = 8;
+
+    #[test]
+    fn corridors_connect_dead_ends() {
+        let tiles = openings();
+        let map = map(&tiles, &[&[R, L | R, L | B], &[0, 0, T]]);
+        let navigation = Navigation::new(&tiles, &map, &OPEN, false);
+
+        assert_eq!(navigation.points().len(), 2);
+        assert!(navigation
+            .points()
+            .iter()
+            .all(|p| p.kind() == Kind::DeadEnd));
+        assert_eq!(navigation.corridors().len(), 1);
+
+        let corridor = &navigation.corridors()[0];
+
+        assert_eq!(corridor.cells(), &[(1, 0), (2, 0)]);
+        assert_eq!(corridor.length(), 3);
+        assert_eq!(navigation.point_at((0, 0)), Some(corridor.start()));
+        assert_eq!(navigation.point_at((2, 1)), Some(corridor.end()));
+    }
+
+    #[test]
+    fn junctions_start_a_corridor_on_each_side() {
+        let tiles = openings();
+        let map = map(&tiles, &[&[R, L | R | B, L], &[0, T, 0]]);
+        let navigation = Navigation::new(&tiles, &map, &OPEN, false);
+        let junction = navigation.point_at((1, 0)).unwrap();
+
+        assert_eq!(navigation.points()[junction].kind(), Kind::Junction);
+        assert_eq!(navigation.points().len(), 4);
+        assert_eq!(navigation.corridors_of(junction).count(), 3);
+        assert!(navigation.corridors().iter().all(|c| c.length() == 1));
+    }
+
+    #[test]
+    fn loops_are_broken_up_by_a_waypoint() {
+        let tiles = openings();
+        let map = map(&tiles, &[&[R | B, L | B], &[T | R, T | L]]);
+        let navigation = Navigation::new(&tiles, &map, &OPEN, false);
+
+        assert_eq!(navigation.points().len(), 1);
+        assert_eq!(navigation.points()[0].kind(), Kind::Waypoint);
+        assert_eq!(navigation.corridors().len(), 1);
+
+        let corridor = &navigation.corridors()[0];
+
+        assert_eq!((corridor.start(), corridor.end()), (0, 0));
+        assert_eq!(corridor.cells().len(), 3);
+    }
+
+    #[test]
+    fn closed_sides_and_unknown_tiles_are_not_connected() {
+        let tiles = openings();
+        let mut map = map(&tiles, &[&[L, R, L | R]]);
+
+        // The last tile is open, but is not one of the tiles the map was generated from.
+        map.insert((2, 0), tile(16, [0, 1, 1, 0]));
+
+        let navigation = Navigation::new(&tiles, &map, &OPEN, false);
+
+        assert_eq!(navigation.points().len(), 2);
+        assert!(navigation
+            .points()
+            .iter()
+            .all(|p| p.kind() == Kind::Isolated));
+        assert_eq!(navigation.point_at((2, 0)), None);
+        assert!(navigation.corridors().is_empty());
+    }
+
+    #[test]
+    fn wrapped_maps_connect_opposite_edges() {
+        let tiles = openings();
+        let map = map(&tiles, &[&[L, R]]);
+        let unwrapped = Navigation::new(&tiles, &map, &OPEN, false);
+        let wrapped = Navigation::new(&tiles, &map, &OPEN, true);
+
+        assert!(unwrapped.corridors().is_empty());
+        assert_eq!(wrapped.corridors().len(), 1);
+        assert!(wrapped.points().iter().all(|p| p.kind() == Kind::DeadEnd));
+    }
+
+    #[test]
+    fn traversal_may_open_whole_tiles() {
+        let tiles = openings();
+        let map = map(&tiles, &[&[0, 0]]);
+        let navigation = Navigation::new(&tiles, &map, &Traversal::Tiles(vec![0]), false);
+
+        assert_eq!(navigation.points().len(), 2);
+        assert_eq!(navigation.corridors()[0].length(), 1);
+    }
+}
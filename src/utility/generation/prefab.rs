@@ -0,0 +1,326 @@
+use crate::collections::grid::{vec::VecGrid, Grid, Idx};
+
+use super::{
+    adjacency::Adjacency,
+    constraint::Constraint,
+    tile::{Node, RawTile, Tile},
+    wfc::{Error, Generator, Wave},
+    Side,
+};
+
+/// A group of tiles that covers several cells and is always placed as a whole.
+///
+/// Sides between two parts of the prefab are only matched with each other, while sides along the
+/// prefab's outline are matched with other tiles as usual. Prefabs are never rotated.
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub struct Prefab<const P: usize>(VecGrid<Tile<P>>);
+
+impl<const P: usize> Prefab<P> {
+    /// Creates a new prefab made up of the provided tiles, where empty cells are not covered
+    pub const fn new(parts: VecGrid<Tile<P>>) -> Self {
+        Self(parts)
+    }
+    /// Creates a new prefab covering a block of the given size from a single raw tile, returning
+    /// `None` if the raw tile's nodes do not cover the block's outline.
+    ///
+    /// The top and bottom sides must contain `width * P` nodes, and the left and right sides must
+    /// contain `height * P` nodes. Every part of the block is given the raw tile's weight.
+    pub fn from_raw(index: usize, raw: &RawTile, (width, height): Idx) -> Option<Self> {
+        for side in Side::Top {
+            let cells = match side {
+                Side::Top | Side::Bottom => width,
+                Side::Left | Side::Right => height,
+            };
+
+            if raw.nodes(side).len() != cells * P {
+                return None;
+            }
+        }
+
+        let mut parts = VecGrid::new(width, height);
+
+        for (x, y) in parts.indexes() {
+            // Sides within the block are never compared by their nodes, so they are left empty.
+            let nodes = |side: Side, outer: bool, offset: usize| -> Vec<Node> {
+                if outer {
+                    raw.nodes(side)[offset * P..(offset + 1) * P].to_vec()
+                } else {
+                    vec![0; P]
+                }
+            };
+            let part = RawTile {
+                nodes: (
+                    nodes(Side::Top, y == 0, x),
+                    nodes(Side::Left, x == 0, y),
+                    nodes(Side::Right, x + 1 == width, y),
+                    nodes(Side::Bottom, y + 1 == height, x),
+                ),
+                interior: vec![],
+                ..raw.clone()
+            };
+
+            parts.insert((x, y), Tile::from_raw(index, &part)?);
+        }
+
+        Some(Self(parts))
+    }
+
+    /// Returns the number of cells covered horizontally and vertically
+    pub fn size(&self) -> Idx {
+        self.0.size()
+    }
+    /// Returns a reference to the tiles making up the prefab
+    pub const fn parts(&self) -> &VecGrid<Tile<P>> {
+        &self.0
+    }
+}
+
+/// Tiles that may be placed within a map, along with prefabs made up of extra tiles
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Prefabs<const P: usize> {
+    /// Every tile, starting with the single-cell tiles and followed by each prefab's parts
+    tiles: Vec<Tile<P>>,
+    /// Index of the tile used for each covered cell of each prefab, stored by position
+    prefabs: Vec<VecGrid<usize>>,
+}
+
+impl<const P: usize> Prefabs<P> {
+    /// Creates a new set of prefabs placed alongside the provided single-cell tiles
+    pub fn new(tiles: &[Tile<P>]) -> Self {
+        Self {
+            tiles: tiles.to_vec(),
+            prefabs: vec![],
+        }
+    }
+
+    /// Returns every tile, starting with the single-cell tiles and followed by each prefab's parts
+    pub fn tiles(&self) -> &[Tile<P>] {
+        &self.tiles
+    }
+    /// Returns the index of the tile used for each covered cell of the given prefab
+    pub fn parts(&self, prefab: usize) -> &VecGrid<usize> {
+        &self.prefabs[prefab]
+    }
+    /// Returns the prefab that the provided tile is a part of, and the part's position within it
+    pub fn part_of(&self, tile: usize) -> Option<(usize, Idx)> {
+        self.prefabs.iter().enumerate().find_map(|(prefab, parts)| {
+            let position = parts
+                .indexes()
+                .into_iter()
+                .find(|i| parts.get(*i) == Some(&tile))?;
+
+            Some((prefab, position))
+        })
+    }
+    /// Returns the tiles that may be placed next to each other, where the parts of each prefab
+    /// only fit against each other along the sides they share
+    pub fn adjacency(&self) -> Adjacency {
+        let mut rules = Adjacency::from_nodes(&self.tiles);
+
+        for parts in &self.prefabs {
+            for index in parts.indexes() {
+                let Some(&tile) = parts.get(index) else {
+                    continue;
+                };
+
+                for side in Side::Top {
+                    let neighbor = side.neighbor(index, parts.size(), false);
+
+                    if let Some(&other) = neighbor.and_then(|n| parts.get(n)) {
+                        rules.link(tile, side, other);
+                    }
+                }
+            }
+        }
+
+        rules
+    }
+
+    /// Adds a prefab, returning its index
+    pub fn add(&mut self, prefab: &Prefab<P>) -> usize {
+        let parts = prefab.parts();
+        let mut indexes = VecGrid::new(parts.width(), parts.height());
+
+        for index in parts.indexes() {
+            if let Some(tile) = parts.get(index) {
+                indexes.insert(index, self.tiles.len());
+                self.tiles.push(*tile);
+            }
+        }
+
+        self.prefabs.push(indexes);
+        self.prefabs.len() - 1
+    }
+    /// Creates a new generator for a map of the given size that places both single-cell tiles and
+    /// whole prefabs
+    pub fn generator(&self, width: usize, height: usize) -> Generator<P> {
+        let mut generator = Generator::with_adjacency(width, height, &self.tiles, self.adjacency());
+        let mut inner = vec![];
+
+        for parts in &self.prefabs {
+            for index in parts.indexes() {
+                let Some(&tile) = parts.get(index) else {
+                    continue;
+                };
+                let sides = Side::Top
+                    .into_iter()
+                    .filter(|side| {
+                        side.neighbor(index, parts.size(), false)
+                            .is_some_and(|n| parts.get(n).is_some())
+                    })
+                    .collect();
+
+                inner.push((tile, sides));
+            }
+        }
+
+        generator.constrain(Whole(inner));
+        generator
+    }
+}
+
+/// Removes prefab parts from cells where the rest of their prefab would not fit within the map,
+/// stored as each part and the sides it shares with other parts
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+struct Whole(Vec<(usize, Vec<Side>)>);
+
+impl<const P: usize> Constraint<P> for Whole {
+    fn initialize(&self, _: &[Tile<P>], wave: &mut Wave, wrap: bool) -> Result<Vec<Idx>, Error> {
+        let (width, height) = wave.size();
+        let mut changed = vec![];
+
+        for y in 0..height {
+            for x in 0..width {
+                for (tile, sides) in &self.0 {
+                    let outside = sides
+                        .iter()
+                        .any(|side| side.neighbor((x, y), (width, height), wrap).is_none());
+
+                    if outside && wave.ban((x, y), *tile) {
+                        changed.push((x, y));
+                    }
+                }
+
+                if wave.count((x, y)) == 0 {
+                    return Err(Error::Contradiction((x, y)));
+                }
+            }
+        }
+
+        Ok(changed)
+    }
+    fn enforce(&self, _: &[Tile<P>], _: &mut Wave, _: &[Idx], _: bool) -> Result<Vec<Idx>, Error> {
+        Ok(vec![])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utility::generation::fixtures::raw;
+
+    /// Returns a set of prefabs made up of a blank tile and a blank prefab two cells wide
+    fn prefabs() -> Prefabs<1> {
+        let blank = Tile::from_raw(0, &raw(&[0], &[0], &[0], &[0])).unwrap();
+        let wide = Prefab::from_raw(1, &raw(&[0, 0], &[0], &[0], &[0, 0]), (2, 1)).unwrap();
+        let mut prefabs = Prefabs::new(&[blank]);
+
+        assert_eq!(prefabs.add(&wide), 0);
+        prefabs
+    }
+
+    #[test]
+    fn raw_prefabs_split_their_outline() {
+        let prefab = Prefab::<1>::from_raw(0, &raw(&[1, 2], &[3], &[4], &[5, 6]), (2, 1)).unwrap();
+        let left = prefab.parts().get((0, 0)).unwrap();
+        let right = prefab.parts().get((1, 0)).unwrap();
+
+        assert_eq!(prefab.size(), (2, 1));
+        assert_eq!(
+            Side::Top
+                .into_iter()
+                .map(|s| left.nodes(s)[0])
+                .collect::<Vec<_>>(),
+            [1, 3, 0, 5]
+        );
+        assert_eq!(
+            Side::Top
+                .into_iter()
+                .map(|s| right.nodes(s)[0])
+                .collect::<Vec<_>>(),
+            [2, 0, 4, 6]
+        );
+    }
+
+    #[test]
+    fn raw_prefabs_must_cover_their_outline() {
+        assert_eq!(
+            Prefab::<1>::from_raw(0, &raw(&[1], &[3], &[4], &[5, 6]), (2, 1)),
+            None
+        );
+        assert_eq!(
+            Prefab::<1>::from_raw(0, &raw(&[1, 2], &[3, 3], &[4], &[5, 6]), (2, 1)),
+            None
+        );
+    }
+
+    #[test]
+    fn parts_only_fit_each_other_along_shared_sides() {
+        let prefabs = prefabs();
+        let rules = prefabs.adjacency();
+
+        assert_eq!(prefabs.tiles().len(), 3);
+        assert_eq!(prefabs.parts(0).get((1, 0)), Some(&2));
+        assert_eq!(prefabs.part_of(2), Some((0, (1, 0))));
+        assert_eq!(prefabs.part_of(0), None);
+        assert_eq!(rules.get(1, Side::Right), &[2]);
+        assert_eq!(rules.get(2, Side::Left), &[1]);
+        assert_eq!(rules.get(1, Side::Left), &[0, 2]);
+        assert_eq!(rules.get(0, Side::Top), &[0, 1, 2]);
+    }
+
+    #[test]
+    fn prefabs_are_placed_whole() {
+        let mut placed = false;
+
+        for seed in 0..10 {
+            let map = prefabs()
+                .generator(3, 3)
+                .with_seed(seed)
+                .run(false)
+                .unwrap();
+
+            // Both parts of the prefab look alike, so each row must hold whole pairs of them.
+            for y in 0..3 {
+                let mut run = 0;
+
+                for x in 0..3 {
+                    if map.get((x, y)).unwrap().index() == 1 {
+                        run += 1;
+                    } else {
+                        assert_eq!(run % 2, 0);
+                        run = 0;
+                    }
+                }
+
+                assert_eq!(run % 2, 0);
+                placed |= map.get((0, y)).unwrap().index() == 1;
+            }
+        }
+
+        assert!(placed);
+    }
+
+    #[test]
+    fn prefabs_that_can_not_fit_are_an_error() {
+        let mut prefabs = Prefabs::<1>::new(&[]);
+        let wide = Prefab::from_raw(0, &raw(&[0, 0], &[0], &[0], &[0, 0]), (2, 1)).unwrap();
+
+        prefabs.add(&wide);
+
+        assert!(matches!(
+            prefabs.generator(1, 1).run(false),
+            Err(Error::Exhausted(explanation)) if explanation.cell() == (0, 0)
+        ));
+    }
+}
@@ -0,0 +1,420 @@
+use std::{
+    fmt::Display,
+    path::{Path, PathBuf},
+};
+
+use bevy::utils::HashMap;
+use image::{
+    imageops::{self, FilterType},
+    Rgba, RgbaImage,
+};
+
+use crate::collections::grid::{vec::VecGrid, Grid};
+
+use super::{
+    sprite::{Source, Sprites},
+    tile::{Node, Tile},
+    Rotation, Side,
+};
+
+/// Colour of wall nodes
+const WALL: Rgba<u8> = Rgba([40, 40, 48, 255]);
+
+/// Colours of every other node, which are usually paths, in order of their value and repeated
+const PATHS: [Rgba<u8>; 8] = [
+    Rgba([230, 230, 220, 255]),
+    Rgba([170, 220, 150, 255]),
+    Rgba([150, 200, 240, 255]),
+    Rgba([240, 210, 140, 255]),
+    Rgba([230, 160, 160, 255]),
+    Rgba([200, 170, 230, 255]),
+    Rgba([160, 230, 220, 255]),
+    Rgba([240, 180, 220, 255]),
+];
+
+/// Colour of the grid lines and the shadow behind coordinates
+const LINE: Rgba<u8> = Rgba([0, 0, 0, 160]);
+
+/// Colour of coordinates
+const TEXT: Rgba<u8> = Rgba([255, 255, 255, 255]);
+
+/// Digits followed by a comma, each stored as five rows of three pixels from top to bottom
+const GLYPHS: [[u8; 5]; 11] = [
+    [0b111, 0b101, 0b101, 0b101, 0b111],
+    [0b010, 0b110, 0b010, 0b010, 0b111],
+    [0b111, 0b001, 0b111, 0b100, 0b111],
+    [0b111, 0b001, 0b111, 0b001, 0b111],
+    [0b101, 0b101, 0b111, 0b001, 0b001],
+    [0b111, 0b100, 0b111, 0b001, 0b111],
+    [0b111, 0b100, 0b111, 0b101, 0b111],
+    [0b111, 0b001, 0b001, 0b001, 0b001],
+    [0b111, 0b101, 0b111, 0b101, 0b111],
+    [0b111, 0b101, 0b111, 0b001, 0b111],
+    [0b000, 0b000, 0b000, 0b010, 0b100],
+];
+
+/// Error that may occur while saving a preview
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub enum Error {
+    /// The image could not be written, stored as the reason
+    Write(String),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Write(reason) => write!(f, "could not write preview: {reason}"),
+        }
+    }
+}
+
+/// Renders generated maps into images on the CPU, without needing a window or GPU
+#[derive(Clone, Debug)]
+pub struct Preview {
+    /// Width and height of each cell in pixels
+    cell: u32,
+    /// Directory that sprite paths are relative to
+    assets: PathBuf,
+    /// Whether grid lines and coordinates are drawn over the map
+    overlay: bool,
+    /// Value of nodes that are drawn as walls
+    wall: Node,
+    /// Every image that has been loaded, or `None` if it could not be loaded, stored by path
+    images: HashMap<String, Option<RgbaImage>>,
+}
+
+impl Preview {
+    /// Creates a new preview that draws each cell with the given width and height in pixels
+    pub fn new(cell: u32) -> Self {
+        Self {
+            cell: cell.max(1),
+            assets: PathBuf::from("assets"),
+            overlay: false,
+            wall: 1,
+            images: HashMap::default(),
+        }
+    }
+    /// Sets the directory that sprite paths are relative to, which defaults to `assets`
+    pub fn with_assets(mut self, assets: impl AsRef<Path>) -> Self {
+        self.assets = assets.as_ref().to_path_buf();
+        self.images.clear();
+        self
+    }
+    /// Sets whether grid lines and the coordinates of each cell are drawn over the map
+    pub const fn with_overlay(mut self, overlay: bool) -> Self {
+        self.overlay = overlay;
+        self
+    }
+    /// Sets the value of nodes that are drawn as walls in schematics, which defaults to `1`
+    pub const fn with_walls(mut self, wall: Node) -> Self {
+        self.wall = wall;
+        self
+    }
+
+    /// Renders the provided map, drawing each tile from its sprite if it has one that can be
+    /// loaded, and as a schematic of its nodes otherwise.
+    ///
+    /// Schematics split each cell into a wedge per side, coloured by the side's nodes, with walls
+    /// dark and paths light. Empty cells are left transparent.
+    pub fn render<const P: usize>(
+        &mut self,
+        map: &VecGrid<Tile<P>>,
+        sprites: &Sprites,
+    ) -> RgbaImage {
+        let (width, height) = map.size();
+        let mut image = RgbaImage::new(width as u32 * self.cell, height as u32 * self.cell);
+
+        for (x, y) in map.indexes() {
+            let Some(tile) = map.get((x, y)) else {
+                continue;
+            };
+            let sprite = sprites
+                .index_of(tile)
+                .and_then(|s| self.sprite(&sprites.sources()[s], tile.rotation()));
+            let cell = sprite.unwrap_or_else(|| self.schematic(tile));
+
+            imageops::overlay(
+                &mut image,
+                &cell,
+                (x as u32 * self.cell).into(),
+                (y as u32 * self.cell).into(),
+            );
+        }
+
+        if self.overlay {
+            self.draw_overlay(&mut image, (width, height));
+        }
+
+        image
+    }
+    /// Renders the provided map and saves it to the given path, in the format matching the path's
+    /// extension
+    pub fn save<const P: usize>(
+        &mut self,
+        map: &VecGrid<Tile<P>>,
+        sprites: &Sprites,
+        path: impl AsRef<Path>,
+    ) -> Result<(), Error> {
+        self.render(map, sprites)
+            .save(path)
+            .map_err(|e| Error::Write(e.to_string()))
+    }
+
+    /// Returns the provided sprite scaled to fit a cell and rotated by the given rotation, or
+    /// `None` if its image could not be loaded or it extends outside of its image
+    fn sprite(&mut self, source: &Source, rotation: Rotation) -> Option<RgbaImage> {
+        let assets = &self.assets;
+        let image = self
+            .images
+            .entry(source.path().to_string())
+            .or_insert_with(|| Some(image::open(assets.join(source.path())).ok()?.into_rgba8()))
+            .as_ref()?;
+
+        let sprite = match source.region() {
+            Some(region) => {
+                let end = region.position + region.size;
+
+                if end.x > image.width() || end.y > image.height() {
+                    return None;
+                }
+
+                let (x, y) = (region.position.x, region.position.y);
+
+                imageops::crop_imm(image, x, y, region.size.x, region.size.y).to_image()
+            }
+            None => image.clone(),
+        };
+        let sprite = imageops::resize(&sprite, self.cell, self.cell, FilterType::Nearest);
+
+        Some(match rotation {
+            Rotation::D0 => sprite,
+            Rotation::D90 => imageops::rotate90(&sprite),
+            Rotation::D180 => imageops::rotate180(&sprite),
+            Rotation::D270 => imageops::rotate270(&sprite),
+        })
+    }
+    /// Returns a schematic of the provided tile's nodes, filling a cell
+    fn schematic<const P: usize>(&self, tile: &Tile<P>) -> RgbaImage {
+        let size = self.cell as f32;
+
+        RgbaImage::from_fn(self.cell, self.cell, |x, y| {
+            let (u, v) = ((x as f32 + 0.5) / size, (y as f32 + 0.5) / size);
+            let distances = [
+                (Side::Top, v, u),
+                (Side::Left, u, v),
+                (Side::Right, 1.0 - u, v),
+                (Side::Bottom, 1.0 - v, u),
+            ];
+            // Each pixel belongs to its closest side, at its position along that side.
+            let (side, _, along) = distances
+                .into_iter()
+                .min_by(|a, b| a.1.total_cmp(&b.1))
+                .unwrap_or(distances[0]);
+            let node = ((along * P as f32) as usize).min(P.saturating_sub(1));
+
+            tile.nodes(side).get(node).map_or(WALL, |n| self.color(*n))
+        })
+    }
+    /// Returns the colour used for nodes with the provided value
+    const fn color(&self, node: Node) -> Rgba<u8> {
+        // Values above the wall's value are shifted down, so path colours are used in order.
+        let index = match node {
+            n if n == self.wall => return WALL,
+            n if n > self.wall => n - 1,
+            n => n,
+        };
+
+        PATHS[index as usize % PATHS.len()]
+    }
+    /// Draws grid lines around every cell of a map of the given size, along with each cell's
+    /// coordinates in its top left corner
+    fn draw_overlay(&self, image: &mut RgbaImage, (width, height): (usize, usize)) {
+        for (x, y, pixel) in image.enumerate_pixels_mut() {
+            if x % self.cell == 0 || y % self.cell == 0 {
+                blend(pixel, LINE);
+            }
+        }
+
+        // Coordinates are skipped if they would not fit within their cell.
+        let longest = format!("{},{}", width.saturating_sub(1), height.saturating_sub(1));
+
+        if (longest.len() as u32 * 4 + 3) > self.cell || self.cell < 9 {
+            return;
+        }
+
+        for y in 0..height as u32 {
+            for x in 0..width as u32 {
+                let text = format!("{x},{y}");
+                let origin = (x * self.cell + 2, y * self.cell + 2);
+
+                draw_text(image, &text, (origin.0 + 1, origin.1 + 1), LINE);
+                draw_text(image, &text, origin, TEXT);
+            }
+        }
+    }
+}
+
+/// Blends the provided colour over the pixel using the colour's alpha
+fn blend(pixel: &mut Rgba<u8>, color: Rgba<u8>) {
+    let alpha = u32::from(color[3]);
+
+    for channel in 0..3 {
+        let mixed = u32::from(pixel[channel]) * (255 - alpha) + u32::from(color[channel]) * alpha;
+
+        pixel[channel] = (mixed / 255) as u8;
+    }
+
+    pixel[3] = pixel[3].max(color[3]);
+}
+
+/// Draws the provided digits and commas with their top left corner at the given position, skipping
+/// any pixels outside of the image
+fn draw_text(image: &mut RgbaImage, text: &str, (x, y): (u32, u32), color: Rgba<u8>) {
+    let glyphs = text.chars().filter_map(|c| match c {
+        ',' => Some(GLYPHS[10]),
+        c => c.to_digit(10).map(|d| GLYPHS[d as usize]),
+    });
+
+    for (index, glyph) in glyphs.enumerate() {
+        for (row, bits) in glyph.iter().enumerate() {
+            for column in 0..3 {
+                if bits & (0b100 >> column) == 0 {
+                    continue;
+                }
+
+                let (px, py) = (x + index as u32 * 4 + column, y + row as u32);
+
+                if px < image.width() && py < image.height() {
+                    blend(image.get_pixel_mut(px, py), color);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utility::generation::{fixtures::tile, tile::RawTile};
+
+    /// Returns sprites using the provided sources, indexed by raw tile
+    fn sprites(sources: &[&str]) -> Sprites {
+        let raws: Vec<RawTile> = sources
+            .iter()
+            .map(|source| RawTile {
+                source: (*source).to_string(),
+                ..RawTile::default()
+            })
+            .collect();
+
+        Sprites::new(&raws).unwrap()
+    }
+
+    /// Returns an empty directory for the given test's images
+    fn assets(test: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("preview-{}-{test}", std::process::id()));
+
+        std::fs::create_dir_all(&path).unwrap();
+        path
+    }
+
+    #[test]
+    fn schematics_colour_each_side() {
+        let mut map = VecGrid::new(2, 1);
+
+        map.insert((0, 0), tile(0, [1, 0, 2, 3]));
+
+        let image = Preview::new(8).render(&map, &sprites(&[]));
+
+        assert_eq!(image.dimensions(), (16, 8));
+        assert_eq!(*image.get_pixel(4, 0), WALL);
+        assert_eq!(*image.get_pixel(0, 4), PATHS[0]);
+        assert_eq!(*image.get_pixel(7, 4), PATHS[1]);
+        assert_eq!(*image.get_pixel(4, 7), PATHS[2]);
+        assert_eq!(*image.get_pixel(12, 4), Rgba([0, 0, 0, 0]));
+    }
+
+    #[test]
+    fn walls_may_use_any_node() {
+        let mut map = VecGrid::new(1, 1);
+
+        map.insert((0, 0), tile(0, [0, 1, 2, 2]));
+
+        let image = Preview::new(8).with_walls(0).render(&map, &sprites(&[]));
+
+        assert_eq!(*image.get_pixel(4, 0), WALL);
+        assert_eq!(*image.get_pixel(0, 4), PATHS[0]);
+        assert_eq!(*image.get_pixel(4, 7), PATHS[1]);
+    }
+
+    #[test]
+    fn sprites_are_scaled_and_rotated() {
+        let assets = assets("sprites");
+        let (red, blue) = (Rgba([255, 0, 0, 255]), Rgba([0, 0, 255, 255]));
+        let sheet = RgbaImage::from_fn(4, 2, |x, _| if x < 2 { red } else { blue });
+
+        sheet.save(assets.join("sheet.png")).unwrap();
+
+        let mut map = VecGrid::new(3, 1);
+
+        map.insert((0, 0), tile(0, [1; 4]));
+        map.insert((1, 0), tile(1, [1; 4]).rotated(Rotation::D90));
+        map.insert((2, 0), tile(2, [1; 4]));
+
+        let sprites = sprites(&["sheet.png@0,0,2,1", "sheet.png", "missing.png"]);
+        let image = Preview::new(4).with_assets(&assets).render(&map, &sprites);
+
+        assert!((0..4).all(|y| (0..4).all(|x| *image.get_pixel(x, y) == red)));
+        assert_eq!(*image.get_pixel(4, 0), red);
+        assert_eq!(*image.get_pixel(4, 3), blue);
+        assert_eq!(*image.get_pixel(10, 2), WALL);
+
+        std::fs::remove_dir_all(assets).unwrap();
+    }
+
+    #[test]
+    fn sprites_outside_of_their_image_are_drawn_as_schematics() {
+        let assets = assets("bounds");
+
+        RgbaImage::new(2, 2).save(assets.join("small.png")).unwrap();
+
+        let mut map = VecGrid::new(1, 1);
+
+        map.insert((0, 0), tile(0, [1; 4]));
+
+        let sprites = sprites(&["small.png@1,1,2,2"]);
+        let image = Preview::new(4).with_assets(&assets).render(&map, &sprites);
+
+        assert_eq!(*image.get_pixel(2, 2), WALL);
+
+        std::fs::remove_dir_all(assets).unwrap();
+    }
+
+    #[test]
+    fn overlays_draw_grid_lines_and_coordinates() {
+        let mut map = VecGrid::new(2, 2);
+
+        for index in map.indexes() {
+            map.insert(index, tile(0, [0; 4]));
+        }
+
+        let plain = Preview::new(16).render(&map, &sprites(&[]));
+        let overlay = Preview::new(16)
+            .with_overlay(true)
+            .render(&map, &sprites(&[]));
+
+        assert_ne!(overlay.get_pixel(16, 8), plain.get_pixel(16, 8));
+        assert_eq!(overlay.get_pixel(8, 12), plain.get_pixel(8, 12));
+        // The first digit of "1,1" starts with its top row's middle pixel.
+        assert_eq!(*overlay.get_pixel(19, 18), TEXT);
+        assert_eq!(*overlay.get_pixel(18, 18), *plain.get_pixel(18, 18));
+    }
+
+    #[test]
+    fn unsupported_extensions_are_an_error() {
+        let map = VecGrid::<Tile<1>>::new(1, 1);
+        let result = Preview::new(4).save(&map, &sprites(&[]), "preview.unknown");
+
+        assert!(matches!(result, Err(Error::Write(_))));
+    }
+}
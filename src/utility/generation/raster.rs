@@ -0,0 +1,250 @@
+use bevy::utils::HashMap;
+
+use crate::collections::grid::{vec::VecGrid, Grid};
+
+use super::{
+    tile::{Node, RawTile, Tile},
+    Side,
+};
+
+/// Decides the node of each sub-cell that is not set by an interior pattern.
+///
+/// Sub-cells along a tile's border use the node on that side, so this only decides the interior and
+/// corners where two different nodes meet.
+#[derive(Clone, Copy, Debug, Default, Hash, PartialEq, Eq)]
+pub enum Fill {
+    /// Interior sub-cells and mismatched corners use the given node
+    Node(Node),
+    /// Each sub-cell uses the smallest node of its closest sides
+    Min,
+    /// Each sub-cell uses the largest node of its closest sides
+    #[default]
+    Max,
+}
+
+/// Expands maps of tiles into grids of nodes, where each tile covers `P` by `P` sub-cells
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Raster<const P: usize> {
+    /// Decides the node of each sub-cell that is not set by an interior pattern
+    fill: Fill,
+    /// Nodes within the interior of each unrotated tile, keyed by raw tile and stored in rows
+    patterns: HashMap<usize, Vec<Vec<Node>>>,
+}
+
+impl<const P: usize> Raster<P> {
+    /// Creates a new rasterizer that fills each tile's interior using the given rule
+    pub fn new(fill: Fill) -> Self {
+        Self {
+            fill,
+            patterns: HashMap::new(),
+        }
+    }
+    /// Creates a new rasterizer using the interior pattern of each provided raw tile, and filling
+    /// any other tiles using the given rule
+    pub fn from_raws(fill: Fill, raws: &[RawTile]) -> Self {
+        let mut raster = Self::new(fill);
+
+        for (index, raw) in raws.iter().enumerate() {
+            if !raw.interior.is_empty() {
+                raster.add_pattern(index, raw.interior.clone());
+            }
+        }
+
+        raster
+    }
+
+    /// Returns the rule used to fill sub-cells that are not set by an interior pattern
+    pub const fn fill(&self) -> Fill {
+        self.fill
+    }
+    /// Returns the interior pattern of the given raw tile, if any
+    pub fn pattern(&self, raw: usize) -> Option<&[Vec<Node>]> {
+        self.patterns.get(&raw).map(Vec::as_slice)
+    }
+    /// Sets the interior pattern of the given raw tile, stored as rows of nodes from top to bottom.
+    ///
+    /// Patterns must be `P - 2` nodes wide and tall, otherwise the pattern is skipped, returning
+    /// `false`.
+    pub fn add_pattern(&mut self, raw: usize, pattern: Vec<Vec<Node>>) -> bool {
+        let size = P.saturating_sub(2);
+
+        if pattern.len() != size || pattern.iter().any(|row| row.len() != size) {
+            return false;
+        }
+
+        self.patterns.insert(raw, pattern);
+        true
+    }
+
+    /// Returns the nodes of each sub-cell of the provided tile, stored in rows from top to bottom
+    pub fn tile(&self, tile: &Tile<P>) -> [[Node; P]; P] {
+        let mut cells = [[0; P]; P];
+
+        for (y, row) in cells.iter_mut().enumerate() {
+            for (x, cell) in row.iter_mut().enumerate() {
+                *cell = self.node(tile, x, y);
+            }
+        }
+
+        if let Some(pattern) = self.patterns.get(&tile.index()) {
+            let size = pattern.len();
+
+            for (y, row) in pattern.iter().enumerate() {
+                for (x, node) in row.iter().enumerate() {
+                    // Each clockwise quarter turn moves a node from (x, y) to (size - 1 - y, x).
+                    let (mut x, mut y) = (x, y);
+
+                    for _ in 0..usize::from(tile.rotation()) {
+                        (x, y) = (size - 1 - y, x);
+                    }
+
+                    cells[y + 1][x + 1] = *node;
+                }
+            }
+        }
+
+        cells
+    }
+    /// Returns a grid of nodes `P` times the width and height of the provided map, where empty
+    /// cells of the map are left empty
+    pub fn map(&self, map: &VecGrid<Tile<P>>) -> VecGrid<Node> {
+        let (width, height) = map.size();
+        let mut grid = VecGrid::new(width * P, height * P);
+
+        for (x, y) in map.indexes() {
+            let Some(tile) = map.get((x, y)) else {
+                continue;
+            };
+
+            for (dy, row) in self.tile(tile).iter().enumerate() {
+                for (dx, node) in row.iter().enumerate() {
+                    grid.insert((x * P + dx, y * P + dy), *node);
+                }
+            }
+        }
+
+        grid
+    }
+
+    /// Returns the node of the sub-cell at the provided position within the given tile, ignoring
+    /// interior patterns
+    fn node(&self, tile: &Tile<P>, x: usize, y: usize) -> Node {
+        let distances = [y, x, P - 1 - x, P - 1 - y];
+        let closest = distances.into_iter().min().unwrap_or(0);
+
+        if closest > 0 {
+            if let Fill::Node(node) = self.fill {
+                return node;
+            }
+        }
+
+        let nodes = Side::Top
+            .into_iter()
+            .filter(|side| distances[usize::from(*side)] == closest)
+            .map(|side| match side {
+                Side::Top | Side::Bottom => tile.nodes(side)[x],
+                Side::Left | Side::Right => tile.nodes(side)[y],
+            });
+        let (min, max) = nodes.fold((Node::MAX, Node::MIN), |(min, max), n| {
+            (min.min(n), max.max(n))
+        });
+
+        match self.fill {
+            _ if min == max => min,
+            Fill::Node(node) => node,
+            Fill::Min => min,
+            Fill::Max => max,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utility::generation::{fixtures::filled, Rotation};
+
+    #[test]
+    fn fill_decides_interiors_and_mismatched_corners() {
+        let tile = Tile::<3>::from_raw(0, &filled::<3>([1, 2, 2, 1])).unwrap();
+
+        assert_eq!(
+            Raster::new(Fill::Max).tile(&tile),
+            [[2, 1, 2], [2, 2, 2], [2, 1, 2]]
+        );
+        assert_eq!(
+            Raster::new(Fill::Min).tile(&tile),
+            [[1, 1, 1], [2, 1, 2], [1, 1, 1]]
+        );
+        assert_eq!(
+            Raster::new(Fill::Node(7)).tile(&tile),
+            [[7, 1, 7], [2, 7, 2], [7, 1, 7]]
+        );
+    }
+
+    #[test]
+    fn matching_corners_keep_their_node() {
+        let tile = Tile::<3>::from_raw(0, &filled::<3>([1, 1, 2, 2])).unwrap();
+
+        assert_eq!(Raster::new(Fill::Node(7)).tile(&tile)[0][0], 1);
+        assert_eq!(Raster::new(Fill::Node(7)).tile(&tile)[2][2], 2);
+    }
+
+    #[test]
+    fn patterns_rotate_with_their_tile() {
+        let mut raster = Raster::<4>::new(Fill::Max);
+        let tile = Tile::<4>::from_raw(0, &filled::<4>([0; 4])).unwrap();
+
+        assert!(raster.add_pattern(0, vec![vec![1, 2], vec![3, 4]]));
+
+        let cells = raster.tile(&tile.rotated(Rotation::D90));
+
+        assert_eq!(cells[1], [0, 3, 1, 0]);
+        assert_eq!(cells[2], [0, 4, 2, 0]);
+        assert_eq!(raster.tile(&tile)[1], [0, 1, 2, 0]);
+    }
+
+    #[test]
+    fn patterns_of_the_wrong_size_are_skipped() {
+        let mut raster = Raster::<4>::new(Fill::Max);
+
+        assert!(!raster.add_pattern(0, vec![vec![1, 2]]));
+        assert!(!raster.add_pattern(0, vec![vec![1], vec![2, 3]]));
+        assert_eq!(raster.pattern(0), None);
+
+        let raws = [
+            filled::<4>([0; 4]),
+            RawTile {
+                interior: vec![vec![1; 3]; 3],
+                ..filled::<4>([0; 4])
+            },
+            RawTile {
+                interior: vec![vec![1; 2]; 2],
+                ..filled::<4>([0; 4])
+            },
+        ];
+        let raster = Raster::<4>::from_raws(Fill::Min, &raws);
+
+        assert_eq!(raster.fill(), Fill::Min);
+        assert_eq!(raster.pattern(0), None);
+        assert_eq!(raster.pattern(1), None);
+        assert_eq!(raster.pattern(2), Some(&[vec![1, 1], vec![1, 1]][..]));
+    }
+
+    #[test]
+    fn maps_leave_empty_cells_empty() {
+        let raster = Raster::<3>::new(Fill::Max);
+        let mut map = VecGrid::new(2, 1);
+
+        map.insert(
+            (1, 0),
+            Tile::<3>::from_raw(0, &filled::<3>([5; 4])).unwrap(),
+        );
+
+        let grid = raster.map(&map);
+
+        assert_eq!(grid.size(), (6, 3));
+        assert_eq!(grid.get((2, 2)), None);
+        assert_eq!(grid.get((3, 0)), Some(&5));
+        assert_eq!(grid.get((5, 2)), Some(&5));
+    }
+}
@@ -0,0 +1,295 @@
+use std::fmt::Display;
+
+use crate::collections::grid::Idx;
+
+use super::wfc::{Error, Generator, Wave};
+
+/// Default maximum number of guesses made while searching a single map for a solution
+pub const DEFAULT_BUDGET: usize = 100_000;
+
+/// The smallest map found that cannot be filled, which proves that a larger map cannot be filled
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub struct Counterexample {
+    /// Position of the counterexample within the full map
+    offset: Idx,
+    /// Size of the counterexample
+    size: Idx,
+    /// Whether cells on opposite edges of the counterexample are neighbours
+    wrap: bool,
+    /// Cells that are pinned within the counterexample, relative to its offset
+    pins: Vec<(Idx, usize)>,
+}
+
+impl Counterexample {
+    /// Returns the position of the counterexample within the full map
+    pub const fn offset(&self) -> Idx {
+        self.offset
+    }
+    /// Returns the size of the counterexample
+    pub const fn size(&self) -> Idx {
+        self.size
+    }
+    /// Returns `true` if cells on opposite edges of the counterexample are neighbours
+    pub const fn wrap(&self) -> bool {
+        self.wrap
+    }
+    /// Returns the cells that are pinned within the counterexample, relative to its offset
+    pub fn pins(&self) -> &[(Idx, usize)] {
+        &self.pins
+    }
+}
+
+impl Display for Counterexample {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (w, h) = self.size;
+        let (x, y) = self.offset;
+
+        write!(f, "no {w}x{h} map at ({x}, {y})")?;
+
+        if self.wrap {
+            write!(f, " with wrapping edges")?;
+        }
+        for (i, ((px, py), tile)) in self.pins.iter().enumerate() {
+            let joiner = if i == 0 { " with tile" } else { ", tile" };
+            write!(f, "{joiner} {tile} pinned at ({px}, {py})")?;
+        }
+
+        write!(f, " can be filled")
+    }
+}
+
+/// Whether a generator is able to fill its map
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub enum Solvability {
+    /// At least one way to fill the map exists
+    Solvable,
+    /// The map can never be filled, as shown by the provided counterexample
+    Unsolvable(Counterexample),
+    /// The search ran out of guesses before reaching an answer
+    Unknown,
+    /// The generator's pins or constraints are invalid, such as a pin outside of the map
+    Invalid(Error),
+}
+
+/// Result of an exhaustive search of a single map
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+enum Outcome {
+    /// A solution was found
+    Found,
+    /// Every possible solution was ruled out
+    Exhausted,
+    /// The search ran out of guesses
+    OutOfBudget,
+}
+
+impl Solvability {
+    /// Decides whether the generator's map can be filled using its tiles, pinned cells, and
+    /// constraints.
+    ///
+    /// If it cannot, every smaller map within it is searched, and the smallest one that also cannot
+    /// be filled is returned. Smaller maps only enforce the constraints that can be enforced within
+    /// a window of the map. `budget` limits the number of guesses made while searching each map.
+    pub fn check<const P: usize>(generator: &Generator<P>, wrap: bool, budget: usize) -> Self {
+        let (width, height) = generator.size();
+
+        match search_window(generator, wrap, budget) {
+            Ok(Outcome::Found) => return Self::Solvable,
+            Ok(Outcome::OutOfBudget) => return Self::Unknown,
+            Ok(Outcome::Exhausted) => {}
+            Err(error) => return Self::Invalid(error),
+        }
+
+        // Smaller windows of a graph do not keep its connections, so only its full map is searched.
+        let mut sizes: Vec<Idx> = (1..=height)
+            .flat_map(|h| (1..=width).map(move |w| (w, h)))
+            .filter(|size| *size != (width, height) && generator.graph().is_none())
+            .collect();
+
+        sizes.sort_by_key(|(w, h)| (w * h, w.abs_diff(*h)));
+
+        for size in sizes {
+            let mut seen = vec![];
+
+            for y in 0..=height - size.1 {
+                for x in 0..=width - size.0 {
+                    let window = generator.window((x, y), size, wrap);
+                    let pins = sorted_pins(&window);
+
+                    // Windows without constraints that contain the same pins at the same positions
+                    // are identical.
+                    if window.constraints().is_empty() && seen.contains(&pins) {
+                        continue;
+                    }
+                    if search_window(&window, false, budget) == Ok(Outcome::Exhausted) {
+                        return Self::Unsolvable(Counterexample {
+                            offset: (x, y),
+                            size,
+                            wrap: false,
+                            pins,
+                        });
+                    }
+
+                    seen.push(pins);
+                }
+            }
+        }
+
+        Self::Unsolvable(Counterexample {
+            offset: (0, 0),
+            size: (width, height),
+            wrap,
+            pins: sorted_pins(generator),
+        })
+    }
+
+    /// Returns `true` if the map is known to be solvable
+    pub const fn is_solvable(&self) -> bool {
+        matches!(self, Self::Solvable)
+    }
+}
+
+/// Returns the generator's pinned cells, sorted by index
+fn sorted_pins<const P: usize>(generator: &Generator<P>) -> Vec<(Idx, usize)> {
+    let mut pins = generator.pins().to_vec();
+
+    pins.sort_unstable();
+    pins
+}
+
+/// Exhaustively searches the generator's map for a solution, failing if the generator's pins or
+/// constraints are invalid
+fn search_window<const P: usize>(
+    generator: &Generator<P>,
+    wrap: bool,
+    mut budget: usize,
+) -> Result<Outcome, Error> {
+    match generator.prepare(generator.size(), generator.pins(), wrap) {
+        Ok(wave) => Ok(search(generator, wave, wrap, &mut budget)),
+        Err(error @ (Error::OutOfBounds(_) | Error::UnknownTile(_) | Error::GridOnly)) => {
+            Err(error)
+        }
+        Err(_) => Ok(Outcome::Exhausted),
+    }
+}
+
+/// Searches for a solution by guessing a tile for the cell with the fewest possible tiles, undoing
+/// the guess if it leads to a contradiction
+fn search<const P: usize>(
+    generator: &Generator<P>,
+    wave: Wave,
+    wrap: bool,
+    budget: &mut usize,
+) -> Outcome {
+    let (width, height) = wave.size();
+    let index = (0..height)
+        .flat_map(|y| (0..width).map(move |x| (x, y)))
+        .filter(|index| wave.count(*index) > 1)
+        .min_by_key(|index| wave.count(*index));
+
+    let Some(index) = index else {
+        return Outcome::Found;
+    };
+
+    for tile in wave.possible(index) {
+        if *budget == 0 {
+            return Outcome::OutOfBudget;
+        }
+
+        *budget -= 1;
+
+        let mut guess = wave.clone();
+        guess.collapse(index, tile);
+
+        if generator.propagate(&mut guess, vec![index], wrap).is_err() {
+            continue;
+        }
+
+        match search(generator, guess, wrap, budget) {
+            Outcome::Exhausted => {}
+            outcome => return outcome,
+        }
+    }
+
+    Outcome::Exhausted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utility::generation::fixtures::tile;
+
+    #[test]
+    fn matching_tiles_are_solvable() {
+        let generator = Generator::new(3, 3, &[tile(0, [0; 4]), tile(1, [1; 4])]);
+
+        assert!(Solvability::check(&generator, false, DEFAULT_BUDGET).is_solvable());
+    }
+
+    #[test]
+    fn smallest_failing_window_is_reported() {
+        // The tile's right side never matches its left side, so no two tiles can sit side by side.
+        let generator = Generator::new(3, 3, &[tile(0, [0, 0, 1, 0])]);
+
+        let Solvability::Unsolvable(example) =
+            Solvability::check(&generator, false, DEFAULT_BUDGET)
+        else {
+            panic!("expected the map to be unsolvable");
+        };
+
+        assert_eq!(example.size(), (2, 1));
+        assert_eq!(example.offset(), (0, 0));
+        assert!(example.pins().is_empty());
+    }
+
+    #[test]
+    fn conflicting_pins_are_reported() {
+        let mut generator = Generator::new(3, 3, &[tile(0, [0; 4]), tile(1, [1; 4])]);
+
+        generator.pin((1, 1), 0);
+        generator.pin((2, 1), 1);
+
+        let Solvability::Unsolvable(example) =
+            Solvability::check(&generator, false, DEFAULT_BUDGET)
+        else {
+            panic!("expected the map to be unsolvable");
+        };
+
+        assert_eq!(example.size(), (2, 1));
+        assert_eq!(example.offset(), (1, 1));
+        assert_eq!(example.pins(), [((0, 0), 0), ((1, 0), 1)]);
+        assert_eq!(
+            example.to_string(),
+            "no 2x1 map at (1, 1) with tile 0 pinned at (0, 0), tile 1 pinned at (1, 0) can be filled"
+        );
+    }
+
+    #[test]
+    fn invalid_pins_are_reported() {
+        let mut generator = Generator::new(3, 3, &[tile(0, [0; 4])]);
+
+        generator.pin((5, 0), 0);
+
+        assert_eq!(
+            Solvability::check(&generator, false, DEFAULT_BUDGET),
+            Solvability::Invalid(Error::OutOfBounds((5, 0)))
+        );
+
+        generator.clear_pins();
+        generator.pin((0, 0), 3);
+
+        assert_eq!(
+            Solvability::check(&generator, false, DEFAULT_BUDGET),
+            Solvability::Invalid(Error::UnknownTile((0, 0)))
+        );
+    }
+
+    #[test]
+    fn running_out_of_guesses_is_unknown() {
+        let generator = Generator::new(3, 3, &[tile(0, [0; 4]), tile(1, [1; 4])]);
+
+        assert_eq!(
+            Solvability::check(&generator, false, 0),
+            Solvability::Unknown
+        );
+    }
+}
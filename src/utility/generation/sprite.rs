@@ -0,0 +1,514 @@
+use std::fmt::Display;
+
+use bevy::{
+    asset::{AssetServer, Assets, Handle},
+    math::{UVec2, Vec2},
+    render::{
+        render_resource::{Extent3d, TextureDimension, TextureFormat},
+        texture::{Image, TextureFormatPixelInfo},
+    },
+    sprite::{Rect, TextureAtlas},
+};
+
+use super::tile::{RawTile, Tile};
+
+/// Error that may occur while resolving or packing sprites
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub enum Error {
+    /// The source of the raw tile at the given index could not be parsed
+    InvalidSource(usize, String),
+    /// The number of provided images does not match the number of image paths
+    MissingImages(usize, usize),
+    /// The region of the sprite at the given index extends outside of its image
+    OutOfBounds(usize),
+    /// The image at the given path could not be converted into a supported format
+    UnsupportedFormat(String),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::InvalidSource(tile, source) => {
+                write!(f, "tile {tile}: invalid source {source:?}")
+            }
+            Self::MissingImages(expected, found) => {
+                write!(f, "expected {expected} images, found {found}")
+            }
+            Self::OutOfBounds(sprite) => write!(f, "sprite {sprite} extends outside of its image"),
+            Self::UnsupportedFormat(path) => write!(f, "{path}: unsupported image format"),
+        }
+    }
+}
+
+/// Area of an image in pixels
+#[derive(Clone, Copy, Debug, Default, Hash, PartialEq, Eq)]
+pub struct Region {
+    /// Position of the region's top left corner
+    pub position: UVec2,
+    /// Width and height of the region
+    pub size: UVec2,
+}
+
+/// Image used to display a tile, parsed from a raw tile's source
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub struct Source {
+    /// Asset path of the image
+    path: String,
+    /// Area of the image that is used, or `None` if the whole image is used
+    region: Option<Region>,
+}
+
+impl Source {
+    /// Parses the provided source, returning `None` if it is empty or invalid.
+    ///
+    /// Sources are an asset path, optionally followed by `@x,y,width,height` to only use an area
+    /// of the image, such as `tiles/sheet.png@16,0,16,16`. Paths may contain `@` themselves, such
+    /// as `tiles/grass@2x.png`, as long as what follows the last `@` does not look like an area,
+    /// which is either empty, a number, or contains a comma.
+    pub fn parse(source: &str) -> Option<Self> {
+        let area = source.rsplit_once('@').filter(|(_, region)| {
+            region.is_empty() || region.contains(',') || region.trim().parse::<u32>().is_ok()
+        });
+        let Some((path, region)) = area else {
+            return (!source.is_empty()).then(|| Self {
+                path: source.to_string(),
+                region: None,
+            });
+        };
+
+        let values: Vec<u32> = region
+            .split(',')
+            .map(|v| v.trim().parse().ok())
+            .collect::<Option<_>>()?;
+        let &[x, y, width, height] = values.as_slice() else {
+            return None;
+        };
+
+        if path.is_empty() || width == 0 || height == 0 {
+            return None;
+        }
+
+        Some(Self {
+            path: path.to_string(),
+            region: Some(Region {
+                position: UVec2::new(x, y),
+                size: UVec2::new(width, height),
+            }),
+        })
+    }
+
+    /// Returns the asset path of the image
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+    /// Returns the area of the image that is used, or `None` if the whole image is used
+    pub const fn region(&self) -> Option<Region> {
+        self.region
+    }
+}
+
+/// Placement of each sprite within a packed texture atlas
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Packing {
+    /// Width and height of the atlas
+    size: UVec2,
+    /// Area of each sprite within its image, indexed by sprite
+    regions: Vec<Region>,
+    /// Index of each sprite's image, indexed by sprite
+    images: Vec<usize>,
+    /// Position of each sprite's top left corner within the atlas, indexed by sprite
+    positions: Vec<UVec2>,
+}
+
+impl Packing {
+    /// Returns the width and height of the atlas
+    pub const fn size(&self) -> UVec2 {
+        self.size
+    }
+    /// Returns the area covered by the given sprite within the atlas
+    pub fn rect(&self, sprite: usize) -> Rect {
+        let min = self.positions[sprite];
+        let max = min + self.regions[sprite].size;
+
+        Rect {
+            min: Vec2::new(min.x as f32, min.y as f32),
+            max: Vec2::new(max.x as f32, max.y as f32),
+        }
+    }
+    /// Creates a new texture atlas using the provided texture, where each sprite's index within the
+    /// atlas matches its index within the packing
+    pub fn atlas(&self, texture: Handle<Image>) -> TextureAtlas {
+        let size = Vec2::new(self.size.x as f32, self.size.y as f32);
+        let mut atlas = TextureAtlas::new_empty(texture, size);
+
+        for sprite in 0..self.positions.len() {
+            atlas.add_texture(self.rect(sprite));
+        }
+
+        atlas
+    }
+    /// Copies each sprite from the provided images into a new atlas image, where images are
+    /// indexed in the same order as their paths, failing if a sprite's image is missing or too small
+    pub fn image(&self, images: &[&Image], paths: &[&str]) -> Result<Image, Error> {
+        let expected = self.images.iter().map(|i| i + 1).max().unwrap_or(0);
+
+        if images.len() != paths.len() || images.len() < expected {
+            return Err(Error::MissingImages(
+                expected.max(paths.len()),
+                images.len(),
+            ));
+        }
+
+        let format = TextureFormat::Rgba8UnormSrgb;
+        let pixel = format.pixel_size();
+        let converted = images
+            .iter()
+            .zip(paths)
+            .map(|(image, path)| match image.texture_descriptor.format {
+                f if f == format => Ok((*image).clone()),
+                _ => image
+                    .convert(format)
+                    .ok_or_else(|| Error::UnsupportedFormat((*path).to_string())),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let (width, height) = (self.size.x as usize, self.size.y as usize);
+        let mut data = vec![0; width * height * pixel];
+
+        for sprite in 0..self.positions.len() {
+            let image = &converted[self.images[sprite]];
+            let source = image.texture_descriptor.size;
+            let Region { position, size } = self.regions[sprite];
+            let end = position + size;
+
+            if end.x > source.width || end.y > source.height {
+                return Err(Error::OutOfBounds(sprite));
+            }
+
+            let target = self.positions[sprite];
+            let row = size.x as usize * pixel;
+
+            for y in 0..size.y as usize {
+                let from = ((position.y as usize + y) * source.width as usize
+                    + position.x as usize)
+                    * pixel;
+                let to = ((target.y as usize + y) * width + target.x as usize) * pixel;
+                let pixels = image
+                    .data
+                    .get(from..from + row)
+                    .ok_or(Error::OutOfBounds(sprite))?;
+
+                data[to..to + row].copy_from_slice(pixels);
+            }
+        }
+
+        let size = Extent3d {
+            width: self.size.x,
+            height: self.size.y,
+            depth_or_array_layers: 1,
+        };
+
+        Ok(Image::new(size, TextureDimension::D2, data, format))
+    }
+}
+
+/// Sprites used by a tile set, each given a stable index within the tile set's texture atlas
+#[derive(Clone, Debug, Default, Hash, PartialEq, Eq)]
+pub struct Sprites {
+    /// Every distinct sprite, in order of first use
+    sources: Vec<Source>,
+    /// Index of the sprite used by each raw tile, if any
+    raws: Vec<Option<usize>>,
+}
+
+impl Sprites {
+    /// Resolves the source of each provided raw tile, where raw tiles sharing a source share a
+    /// sprite, and raw tiles without a source have no sprite
+    pub fn new(raws: &[RawTile]) -> Result<Self, Error> {
+        let mut sprites = Self::default();
+
+        for (index, raw) in raws.iter().enumerate() {
+            if raw.source.is_empty() {
+                sprites.raws.push(None);
+                continue;
+            }
+
+            let source = Source::parse(&raw.source)
+                .ok_or_else(|| Error::InvalidSource(index, raw.source.clone()))?;
+            let sprite = match sprites.sources.iter().position(|s| *s == source) {
+                Some(sprite) => sprite,
+                None => {
+                    sprites.sources.push(source);
+                    sprites.sources.len() - 1
+                }
+            };
+
+            sprites.raws.push(Some(sprite));
+        }
+
+        Ok(sprites)
+    }
+
+    /// Returns every distinct sprite, in order of their index within the atlas
+    pub fn sources(&self) -> &[Source] {
+        &self.sources
+    }
+    /// Returns the index within the atlas of the sprite used by the given raw tile, if any
+    pub fn index(&self, raw: usize) -> Option<usize> {
+        self.raws.get(raw).copied().flatten()
+    }
+    /// Returns the index within the atlas of the sprite used by the provided tile, if any
+    pub fn index_of<const P: usize>(&self, tile: &Tile<P>) -> Option<usize> {
+        self.index(tile.index())
+    }
+    /// Returns every distinct image path, in order of first use
+    pub fn paths(&self) -> Vec<&str> {
+        let mut paths: Vec<&str> = vec![];
+
+        for source in &self.sources {
+            if !paths.contains(&source.path()) {
+                paths.push(source.path());
+            }
+        }
+
+        paths
+    }
+
+    /// Starts loading each image, returning their handles in the same order as their paths
+    pub fn load(&self, server: &AssetServer) -> Vec<Handle<Image>> {
+        self.paths()
+            .into_iter()
+            .map(|path| server.load(path))
+            .collect()
+    }
+    /// Arranges every sprite into rows no wider than `max_width`, where `sizes` are the sizes of the
+    /// images in the same order as their paths.
+    ///
+    /// Sprites are placed from tallest to shortest, with sprites wider than `max_width` placed on
+    /// their own row.
+    pub fn pack(&self, sizes: &[UVec2], max_width: u32) -> Result<Packing, Error> {
+        let paths = self.paths();
+
+        if sizes.len() != paths.len() {
+            return Err(Error::MissingImages(paths.len(), sizes.len()));
+        }
+
+        let mut packing = Packing::default();
+
+        for (sprite, source) in self.sources.iter().enumerate() {
+            let image = paths.iter().position(|p| *p == source.path()).unwrap_or(0);
+            let region = source.region.unwrap_or(Region {
+                position: UVec2::ZERO,
+                size: sizes[image],
+            });
+            let end = region.position + region.size;
+
+            if end.x > sizes[image].x || end.y > sizes[image].y {
+                return Err(Error::OutOfBounds(sprite));
+            }
+
+            packing.regions.push(region);
+            packing.images.push(image);
+        }
+
+        let mut order: Vec<usize> = (0..self.sources.len()).collect();
+
+        order.sort_by_key(|s| std::cmp::Reverse(packing.regions[*s].size.y));
+        packing.positions = vec![UVec2::ZERO; order.len()];
+
+        let (mut x, mut y, mut row) = (0, 0, 0);
+
+        for sprite in order {
+            let size = packing.regions[sprite].size;
+
+            if x > 0 && x + size.x > max_width {
+                (x, y, row) = (0, y + row, 0);
+            }
+
+            packing.positions[sprite] = UVec2::new(x, y);
+            packing.size = packing.size.max(UVec2::new(x + size.x, y + size.y));
+            x += size.x;
+            row = row.max(size.y);
+        }
+
+        Ok(packing)
+    }
+    /// Packs every sprite into a new atlas once all of the provided images have loaded, returning
+    /// `None` while any are still loading.
+    ///
+    /// Handles must be in the same order as their paths, as returned by `load`.
+    pub fn build(
+        &self,
+        handles: &[Handle<Image>],
+        images: &mut Assets<Image>,
+        max_width: u32,
+    ) -> Result<Option<TextureAtlas>, Error> {
+        let Some(loaded) = handles
+            .iter()
+            .map(|h| images.get(h))
+            .collect::<Option<Vec<&Image>>>()
+        else {
+            return Ok(None);
+        };
+
+        let sizes: Vec<UVec2> = loaded
+            .iter()
+            .map(|i| {
+                UVec2::new(
+                    i.texture_descriptor.size.width,
+                    i.texture_descriptor.size.height,
+                )
+            })
+            .collect();
+        let packing = self.pack(&sizes, max_width)?;
+        let image = packing.image(&loaded, &self.paths())?;
+
+        Ok(Some(packing.atlas(images.add(image))))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns raw tiles with the provided sources
+    fn raws(sources: &[&str]) -> Vec<RawTile> {
+        sources
+            .iter()
+            .map(|source| RawTile {
+                source: (*source).to_string(),
+                ..RawTile::default()
+            })
+            .collect()
+    }
+
+    /// Returns an image made up of a single row of the provided pixels
+    fn image(pixels: &[[u8; 4]]) -> Image {
+        let size = Extent3d {
+            width: pixels.len() as u32,
+            height: 1,
+            depth_or_array_layers: 1,
+        };
+
+        Image::new(
+            size,
+            TextureDimension::D2,
+            pixels.concat(),
+            TextureFormat::Rgba8UnormSrgb,
+        )
+    }
+
+    #[test]
+    fn sources_may_use_an_area_of_their_image() {
+        let whole = Source::parse("tiles/grass.png").unwrap();
+        let area = Source::parse("tiles/sheet@2x.png@16, 0,16,8").unwrap();
+
+        assert_eq!(whole.path(), "tiles/grass.png");
+        assert_eq!(whole.region(), None);
+        assert_eq!(area.path(), "tiles/sheet@2x.png");
+        assert_eq!(
+            area.region(),
+            Some(Region {
+                position: UVec2::new(16, 0),
+                size: UVec2::new(16, 8),
+            })
+        );
+    }
+
+    #[test]
+    fn invalid_sources_are_rejected() {
+        for source in [
+            "",
+            "@0,0,1,1",
+            "sheet.png@0,0,1",
+            "sheet.png@0,0,1,1,1",
+            "sheet.png@0,0,0,1",
+            "sheet.png@0,-1,1,1",
+            "sheet.png@a,0,1,1",
+            "sheet.png@",
+        ] {
+            assert_eq!(Source::parse(source), None, "{source}");
+        }
+
+        // Suffixes that are not a list of integers are part of the path.
+        for source in ["grass@2x.png", "grass@large.png"] {
+            assert_eq!(Source::parse(source).unwrap().path(), source);
+        }
+
+        assert_eq!(
+            Sprites::new(&raws(&["a.png", "b.png@1"])),
+            Err(Error::InvalidSource(1, "b.png@1".to_string()))
+        );
+    }
+
+    #[test]
+    fn sprites_are_indexed_in_order_of_first_use() {
+        let sprites =
+            Sprites::new(&raws(&["b.png", "", "a.png@0,0,1,1", "b.png", "a.png"])).unwrap();
+
+        assert_eq!(sprites.sources().len(), 3);
+        assert_eq!(
+            (0..6).map(|raw| sprites.index(raw)).collect::<Vec<_>>(),
+            [Some(0), None, Some(1), Some(0), Some(2), None]
+        );
+        assert_eq!(sprites.paths(), ["b.png", "a.png"]);
+    }
+
+    #[test]
+    fn sprites_are_packed_in_rows() {
+        let sprites = Sprites::new(&raws(&[
+            "small.png@0,0,2,2",
+            "large.png",
+            "small.png@2,0,2,2",
+        ]))
+        .unwrap();
+        let packing = sprites
+            .pack(&[UVec2::new(4, 2), UVec2::new(4, 4)], 6)
+            .unwrap();
+        let rect = |sprite| {
+            let rect = packing.rect(sprite);
+
+            (rect.min.to_array(), rect.max.to_array())
+        };
+
+        assert_eq!(packing.size(), UVec2::new(6, 6));
+        assert_eq!(rect(1), ([0.0, 0.0], [4.0, 4.0]));
+        assert_eq!(rect(0), ([4.0, 0.0], [6.0, 2.0]));
+        assert_eq!(rect(2), ([0.0, 4.0], [2.0, 6.0]));
+        assert_eq!(packing.atlas(Handle::default()).textures.len(), 3);
+    }
+
+    #[test]
+    fn packing_checks_image_sizes() {
+        let sprites = Sprites::new(&raws(&["a.png", "b.png@2,2,4,4"])).unwrap();
+
+        assert_eq!(
+            sprites.pack(&[UVec2::ONE], 16),
+            Err(Error::MissingImages(2, 1))
+        );
+        assert_eq!(
+            sprites.pack(&[UVec2::ONE, UVec2::new(5, 8)], 16),
+            Err(Error::OutOfBounds(1))
+        );
+    }
+
+    #[test]
+    fn packed_images_copy_each_sprite() {
+        let (red, green) = ([255, 0, 0, 255], [0, 255, 0, 255]);
+        let sprites = Sprites::new(&raws(&["sheet.png@1,0,1,1", "sheet.png@0,0,1,1"])).unwrap();
+        let sheet = image(&[red, green]);
+        let packing = sprites.pack(&[UVec2::new(2, 1)], 16).unwrap();
+        let atlas = packing.image(&[&sheet], &sprites.paths()).unwrap();
+
+        assert_eq!(atlas.texture_descriptor.size.width, 2);
+        assert_eq!(atlas.data, [green, red].concat());
+        assert_eq!(
+            packing.image(&[], &sprites.paths()).err(),
+            Some(Error::MissingImages(1, 0))
+        );
+        assert_eq!(
+            packing.image(&[&image(&[red])], &sprites.paths()).err(),
+            Some(Error::OutOfBounds(0))
+        );
+    }
+}
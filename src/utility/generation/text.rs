@@ -0,0 +1,389 @@
+use std::fmt::Display;
+
+use crate::collections::grid::{vec::VecGrid, Grid, Idx};
+
+use super::{
+    raster::Raster,
+    tile::{Node, Tile},
+    Side,
+};
+
+/// Characters drawn for walls, indexed by the neighbouring walls that they connect to, where the
+/// bits from lowest to highest are set for up, down, left, and right
+const WALLS: [char; 16] = [
+    '■', '╵', '╷', '│', '╴', '┘', '┐', '┤', '╶', '└', '┌', '├', '─', '┴', '┬', '┼',
+];
+
+/// Characters drawn for every other node, in order of their value
+const PATHS: [char; 5] = [' ', '·', '░', '▒', '▓'];
+
+/// Character drawn for empty cells
+const EMPTY: char = '?';
+
+/// Character drawn for nodes whose value is too large to be drawn with its own character
+const UNKNOWN: char = '+';
+
+/// Error that may occur while parsing a text map
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub enum Error {
+    /// The line at the given index is a different length from the first line
+    Ragged(usize),
+    /// The text's width and height in characters do not cover a whole, non-zero number of cells
+    Size(Idx),
+    /// The character at the given column and line is not used by the text format
+    UnknownGlyph(char, Idx),
+    /// The cell at the given index does not match any tile
+    UnknownCell(Idx),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Ragged(line) => write!(f, "line {line} is a different length from the first"),
+            Self::Size((width, height)) => {
+                write!(
+                    f,
+                    "{width} by {height} characters is not a whole number of cells"
+                )
+            }
+            Self::UnknownGlyph(glyph, (x, y)) => {
+                write!(f, "unknown character {glyph:?} at column {x} of line {y}")
+            }
+            Self::UnknownCell((x, y)) => write!(f, "cell ({x}, {y}) does not match any tile"),
+        }
+    }
+}
+
+/// Decides how each cell of a map is drawn
+#[derive(Clone, Debug, Default, Hash, PartialEq, Eq)]
+pub enum Style {
+    /// Each cell is drawn as `P` by `P` characters showing its nodes, where walls are drawn as lines
+    /// joined to neighbouring walls
+    #[default]
+    Nodes,
+    /// Each cell is drawn as a single line joining the sides that contain a node other than a wall
+    Sides,
+    /// Each cell is drawn as the chosen character of its tile, indexed by tile, where tiles without
+    /// a character are drawn as they are by `Sides`
+    Glyphs(Vec<char>),
+}
+
+/// Draws maps as text, and reads them back from text drawn in the same style.
+///
+/// Empty cells are drawn as `?`. Every other node is drawn in order of its value, skipping the
+/// wall's value, so with the default wall of `1`, node `0` is drawn as a space and node `2` as `·`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Text<const P: usize> {
+    /// Tiles that text may contain
+    tiles: Vec<Tile<P>>,
+    /// Expands tiles into the nodes that are drawn by the `Nodes` style
+    raster: Raster<P>,
+    /// How each cell is drawn
+    style: Style,
+    /// Value of nodes that are drawn as walls
+    wall: Node,
+}
+
+impl<const P: usize> Text<P> {
+    /// Creates a new text format for maps made up of the provided tiles, drawing their nodes as
+    /// rasterized by the given rasterizer
+    pub fn new(tiles: &[Tile<P>], raster: Raster<P>) -> Self {
+        Self {
+            tiles: tiles.to_vec(),
+            raster,
+            style: Style::Nodes,
+            wall: 1,
+        }
+    }
+    /// Sets how each cell is drawn, which defaults to `Style::Nodes`
+    pub fn with_style(mut self, style: Style) -> Self {
+        self.style = style;
+        self
+    }
+    /// Sets the value of nodes that are drawn as walls, which defaults to `1`
+    pub const fn with_walls(mut self, wall: Node) -> Self {
+        self.wall = wall;
+        self
+    }
+
+    /// Draws the provided map, with each row of characters on its own line
+    pub fn render(&self, map: &VecGrid<Tile<P>>) -> String {
+        let (width, height) = map.size();
+        let mut text = String::new();
+
+        if self.style != Style::Nodes {
+            for y in 0..height {
+                text.extend((0..width).map(|x| map.get((x, y)).map_or(EMPTY, |t| self.glyph(t))));
+                text.push('\n');
+            }
+
+            return text;
+        }
+
+        let nodes = self.raster.map(map);
+        let is_wall = |index: Option<Idx>| {
+            index
+                .and_then(|i| nodes.get(i))
+                .is_some_and(|n| *n == self.wall)
+        };
+
+        for y in 0..height * P {
+            for x in 0..width * P {
+                let glyph = match nodes.get((x, y)) {
+                    None => EMPTY,
+                    Some(node) if *node == self.wall => {
+                        let neighbors = Side::Top
+                            .into_iter()
+                            .map(|side| is_wall(side.neighbor((x, y), nodes.size(), false)));
+                        let connected = neighbors
+                            .zip([1, 4, 8, 2])
+                            .filter(|(wall, _)| *wall)
+                            .fold(0, |mask, (_, bit)| mask | bit);
+
+                        WALLS[connected]
+                    }
+                    Some(node) => {
+                        let index = if *node > self.wall { node - 1 } else { *node };
+
+                        PATHS.get(usize::from(index)).copied().unwrap_or(UNKNOWN)
+                    }
+                };
+
+                text.push(glyph);
+            }
+
+            text.push('\n');
+        }
+
+        text
+    }
+    /// Draws the provided tile on its own
+    pub fn render_tile(&self, tile: &Tile<P>) -> String {
+        let mut map = VecGrid::new(1, 1);
+
+        map.insert((0, 0), *tile);
+        self.render(&map)
+    }
+    /// Reads a map drawn in this format, where each cell uses the first tile that is drawn the same
+    /// way.
+    ///
+    /// Nodes whose value is too large to be drawn with its own character can not be read back.
+    pub fn parse(&self, text: &str) -> Result<VecGrid<Tile<P>>, Error> {
+        let lines: Vec<Vec<char>> = text.lines().map(|l| l.chars().collect()).collect();
+        let columns = lines.first().map_or(0, Vec::len);
+
+        if let Some(line) = lines.iter().position(|l| l.len() != columns) {
+            return Err(Error::Ragged(line));
+        }
+
+        let cell = if self.style == Style::Nodes { P } else { 1 };
+
+        let whole = |length: usize| length > 0 && length.is_multiple_of(cell);
+
+        if !whole(columns) || !whole(lines.len()) {
+            return Err(Error::Size((columns, lines.len())));
+        }
+
+        let mut map = VecGrid::new(columns / cell, lines.len() / cell);
+
+        for index in map.indexes() {
+            let tile = match self.style {
+                Style::Nodes => self.parse_nodes(&lines, index)?,
+                _ => self.parse_glyph(&lines, index)?,
+            };
+
+            if let Some(tile) = tile {
+                map.insert(index, *tile);
+            }
+        }
+
+        Ok(map)
+    }
+
+    /// Returns the tile drawn as the provided character at the given position, or `None` if the
+    /// cell is empty
+    fn parse_glyph(&self, lines: &[Vec<char>], (x, y): Idx) -> Result<Option<&Tile<P>>, Error> {
+        match lines[y][x] {
+            EMPTY => Ok(None),
+            glyph => self
+                .tiles
+                .iter()
+                .find(|t| self.glyph(t) == glyph)
+                .map(Some)
+                .ok_or(Error::UnknownGlyph(glyph, (x, y))),
+        }
+    }
+    /// Returns the tile whose nodes are drawn within the cell at the given index, or `None` if the
+    /// cell is empty
+    fn parse_nodes(&self, lines: &[Vec<char>], (x, y): Idx) -> Result<Option<&Tile<P>>, Error> {
+        let mut pattern = [[0; P]; P];
+        let mut empty = 0;
+
+        for (dy, row) in pattern.iter_mut().enumerate() {
+            for (dx, node) in row.iter_mut().enumerate() {
+                let (column, line) = (x * P + dx, y * P + dy);
+
+                match lines[line][column] {
+                    EMPTY => empty += 1,
+                    glyph => {
+                        *node = self
+                            .node(glyph)
+                            .ok_or(Error::UnknownGlyph(glyph, (column, line)))?;
+                    }
+                }
+            }
+        }
+
+        match empty {
+            0 => self
+                .tiles
+                .iter()
+                .find(|t| self.raster.tile(t) == pattern)
+                .map(Some)
+                .ok_or(Error::UnknownCell((x, y))),
+            e if e == P * P => Ok(None),
+            _ => Err(Error::UnknownCell((x, y))),
+        }
+    }
+    /// Returns the character drawn for the provided tile by the `Sides` and `Glyphs` styles
+    fn glyph(&self, tile: &Tile<P>) -> char {
+        if let Style::Glyphs(glyphs) = &self.style {
+            let glyph = self
+                .tiles
+                .iter()
+                .position(|t| t == tile)
+                .and_then(|i| glyphs.get(i));
+
+            if let Some(glyph) = glyph {
+                return *glyph;
+            }
+        }
+
+        let open = [
+            (Side::Top, 1),
+            (Side::Bottom, 2),
+            (Side::Left, 4),
+            (Side::Right, 8),
+        ]
+        .into_iter()
+        .filter(|(side, _)| tile.nodes(*side).iter().any(|n| *n != self.wall))
+        .fold(0, |mask, (_, bit)| mask | bit);
+
+        WALLS[open]
+    }
+    /// Returns the node drawn as the provided character by the `Nodes` style, if any
+    fn node(&self, glyph: char) -> Option<Node> {
+        if WALLS.contains(&glyph) {
+            return Some(self.wall);
+        }
+
+        let index = PATHS.iter().position(|p| *p == glyph)? as Node;
+
+        Some(if index >= self.wall { index + 1 } else { index })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utility::generation::{
+        fixtures::{filled, raw},
+        raster::Fill,
+    };
+
+    /// Returns a tile made up only of walls, and a tile with walls in its corners
+    fn tiles() -> Vec<Tile<3>> {
+        let cornered = [1, 0, 1];
+
+        vec![
+            Tile::from_raw(0, &filled::<3>([1; 4])).unwrap(),
+            Tile::from_raw(1, &raw(&cornered, &cornered, &cornered, &cornered)).unwrap(),
+        ]
+    }
+
+    /// Returns a map of the wall tile, the cornered tile, and an empty cell
+    fn map(tiles: &[Tile<3>]) -> VecGrid<Tile<3>> {
+        let mut map = VecGrid::new(3, 1);
+
+        map.insert((0, 0), tiles[0]);
+        map.insert((1, 0), tiles[1]);
+        map
+    }
+
+    /// Returns a text format for the provided tiles in the given style
+    fn text(tiles: &[Tile<3>], style: Style) -> Text<3> {
+        Text::new(tiles, Raster::new(Fill::Max)).with_style(style)
+    }
+
+    #[test]
+    fn nodes_are_drawn_with_joined_walls() {
+        let tiles = tiles();
+
+        assert_eq!(
+            text(&tiles, Style::Nodes).render(&map(&tiles)),
+            "┌┬┬╴ ■???\n├┼┤   ???\n└┴┴╴ ■???\n"
+        );
+    }
+
+    #[test]
+    fn walls_may_use_any_node() {
+        let tiles = tiles();
+        let text = text(&tiles, Style::Nodes).with_walls(0);
+
+        assert_eq!(text.render_tile(&tiles[1]), " ╷ \n╶┼╴\n ╵ \n");
+        assert_eq!(
+            text.parse(" ╷ \n╶┼╴\n ╵ \n").unwrap().get((0, 0)),
+            Some(&tiles[1])
+        );
+    }
+
+    #[test]
+    fn sides_and_glyphs_are_drawn_per_cell() {
+        let tiles = tiles();
+
+        assert_eq!(text(&tiles, Style::Sides).render(&map(&tiles)), "■┼?\n");
+        assert_eq!(
+            text(&tiles, Style::Glyphs(vec!['#'])).render(&map(&tiles)),
+            "#┼?\n"
+        );
+    }
+
+    #[test]
+    fn rendered_maps_parse_back() {
+        let tiles = tiles();
+        let map = map(&tiles);
+
+        for style in [Style::Nodes, Style::Sides, Style::Glyphs(vec!['#', '.'])] {
+            let text = text(&tiles, style);
+
+            assert_eq!(text.parse(&text.render(&map)), Ok(map.clone()));
+        }
+    }
+
+    #[test]
+    fn malformed_text_is_an_error() {
+        let tiles = tiles();
+        let nodes = text(&tiles, Style::Nodes);
+        let sides = text(&tiles, Style::Sides);
+
+        assert_eq!(nodes.parse("■ ■\n   \n■ \n"), Err(Error::Ragged(2)));
+        assert_eq!(nodes.parse("■ ■\n   \n"), Err(Error::Size((3, 2))));
+        assert_eq!(nodes.parse(""), Err(Error::Size((0, 0))));
+        assert_eq!(
+            nodes.parse("■ ■\n x \n■ ■\n"),
+            Err(Error::UnknownGlyph('x', (1, 1)))
+        );
+        assert_eq!(
+            nodes.parse("■ ■\n ■ \n■ ■\n"),
+            Err(Error::UnknownCell((0, 0)))
+        );
+        assert_eq!(
+            nodes.parse("■ ■\n ? \n■ ■\n"),
+            Err(Error::UnknownCell((0, 0)))
+        );
+        assert_eq!(
+            sides.parse("■┼?\n■┬?\n"),
+            Err(Error::UnknownGlyph('┬', (1, 1)))
+        );
+    }
+}
@@ -0,0 +1,496 @@
+use std::collections::BTreeMap;
+
+use serde::{ser::SerializeStruct, Deserialize, Serialize, Serializer};
+
+use crate::collections::grid::{vec::VecGrid, Grid};
+
+use super::{adjacency::Adjacency, metadata::Property, Rotation, Side};
+
+pub mod dynamic;
+
+/// Value stored within each node of a tile's side
+pub type Node = u8;
+
+/// Raw tile data, as stored within a tile file.
+///
+/// Fields that are empty are left out of text formats, and are read back as empty when missing.
+#[derive(Clone, Debug, Default, Hash, PartialEq, Eq, Deserialize)]
+pub struct RawTile {
+    /// Identifier used to override or remove the tile from files that include it, or empty if the
+    /// tile can not be referred to
+    #[serde(default)]
+    pub name: String,
+    /// Path to the tile's texture
+    pub source: String,
+    /// Layer that the tile is rendered on
+    pub layer: usize,
+    /// Relative likelihood of the tile being chosen
+    pub weight: i32,
+    /// The tile's nodes, in the order of top, left, right, and bottom
+    pub nodes: (Vec<Node>, Vec<Node>, Vec<Node>, Vec<Node>),
+    /// Nodes within the tile's interior when rasterized, stored in rows from top to bottom, or empty
+    /// to fill the interior automatically
+    #[serde(default)]
+    pub interior: Vec<Vec<Node>>,
+    /// Tags that gameplay systems may query, such as `walkable`
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Typed values that gameplay systems may query, keyed by name
+    #[serde(default)]
+    pub properties: BTreeMap<String, Property>,
+}
+
+impl RawTile {
+    /// Returns a reference to the nodes on the provided side
+    pub fn nodes(&self, side: Side) -> &Vec<Node> {
+        match side {
+            Side::Top => &self.nodes.0,
+            Side::Left => &self.nodes.1,
+            Side::Right => &self.nodes.2,
+            Side::Bottom => &self.nodes.3,
+        }
+    }
+}
+
+/// Raw tile file data, as stored on disk.
+///
+/// Fields that are empty are left out of text formats, and are read back as empty when missing.
+#[derive(Clone, Debug, Default, Hash, PartialEq, Eq, Deserialize)]
+pub struct RawFile {
+    /// The tile set's identifier
+    pub id: usize,
+    /// The file's format version, which older files are upgraded from when loaded through a
+    /// `Migration`
+    #[serde(default)]
+    pub version: usize,
+    /// Number of nodes on each side of a tile
+    pub precision: usize,
+    /// Path to a base file whose tiles are inherited, relative to this file
+    #[serde(default)]
+    pub extends: Option<String>,
+    /// Paths to files whose tiles are added after the base file's tiles, relative to this file
+    #[serde(default)]
+    pub include: Vec<String>,
+    /// Names of inherited tiles that are removed
+    #[serde(default)]
+    pub remove: Vec<String>,
+    /// New weights of inherited tiles, stored as a name and a weight
+    #[serde(default)]
+    pub weights: Vec<(String, i32)>,
+    /// The tiles stored within the file, where tiles named the same as an inherited tile replace it
+    pub tiles: Vec<RawTile>,
+}
+
+// Binary formats read fields by position rather than by name, so they always store every field.
+impl Serialize for RawTile {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let text = serializer.is_human_readable();
+        let mut state = serializer.serialize_struct("RawTile", 8)?;
+
+        optional(&mut state, "name", &self.name, text && self.name.is_empty())?;
+        state.serialize_field("source", &self.source)?;
+        state.serialize_field("layer", &self.layer)?;
+        state.serialize_field("weight", &self.weight)?;
+        state.serialize_field("nodes", &self.nodes)?;
+        optional(
+            &mut state,
+            "interior",
+            &self.interior,
+            text && self.interior.is_empty(),
+        )?;
+        optional(&mut state, "tags", &self.tags, text && self.tags.is_empty())?;
+        optional(
+            &mut state,
+            "properties",
+            &self.properties,
+            text && self.properties.is_empty(),
+        )?;
+        state.end()
+    }
+}
+
+impl Serialize for RawFile {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let text = serializer.is_human_readable();
+        let mut state = serializer.serialize_struct("RawFile", 8)?;
+
+        state.serialize_field("id", &self.id)?;
+        state.serialize_field("version", &self.version)?;
+        state.serialize_field("precision", &self.precision)?;
+        optional(
+            &mut state,
+            "extends",
+            &self.extends,
+            text && self.extends.is_none(),
+        )?;
+        optional(
+            &mut state,
+            "include",
+            &self.include,
+            text && self.include.is_empty(),
+        )?;
+        optional(
+            &mut state,
+            "remove",
+            &self.remove,
+            text && self.remove.is_empty(),
+        )?;
+        optional(
+            &mut state,
+            "weights",
+            &self.weights,
+            text && self.weights.is_empty(),
+        )?;
+        state.serialize_field("tiles", &self.tiles)?;
+        state.end()
+    }
+}
+
+/// Serializes the provided field, or leaves it out if `skip` is `true`
+fn optional<S: SerializeStruct, T: Serialize>(
+    state: &mut S,
+    name: &'static str,
+    value: &T,
+    skip: bool,
+) -> Result<(), S::Error> {
+    if skip {
+        state.skip_field(name)
+    } else {
+        state.serialize_field(name, value)
+    }
+}
+
+/// Generated map data, as stored on disk
+#[derive(Clone, Debug, Default, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RawMap {
+    /// Identifier of the tile set that the map was generated from
+    pub id: usize,
+    /// Number of cells horizontally
+    pub width: usize,
+    /// Number of cells vertically
+    pub height: usize,
+    /// Raw tile and rotation of each cell in rows from top to bottom, or `None` for empty cells
+    pub cells: Vec<Option<(usize, Rotation)>>,
+}
+
+impl RawMap {
+    /// Creates new map data from the provided map, generated from the tile set with the given
+    /// identifier
+    pub fn new<const P: usize>(id: usize, map: &VecGrid<Tile<P>>) -> Self {
+        let (width, height) = map.size();
+        let cells = map
+            .indexes()
+            .into_iter()
+            .map(|index| map.get(index).map(|t| (t.index, t.rotation)))
+            .collect();
+
+        Self {
+            id,
+            width,
+            height,
+            cells,
+        }
+    }
+
+    /// Returns the map using the provided tiles, or `None` if any cell uses a tile variant that is
+    /// not within the tiles or the cells do not fill the map
+    pub fn to_map<const P: usize>(&self, tiles: &[Tile<P>]) -> Option<VecGrid<Tile<P>>> {
+        if self.cells.len() != self.width * self.height {
+            return None;
+        }
+
+        let mut map = VecGrid::new(self.width, self.height);
+
+        for (index, cell) in map.indexes().into_iter().zip(&self.cells) {
+            let Some((raw, rotation)) = cell else {
+                continue;
+            };
+            let tile = tiles
+                .iter()
+                .find(|t| t.index == *raw && t.rotation == *rotation)?;
+
+            map.insert(index, *tile);
+        }
+
+        Some(map)
+    }
+}
+
+/// A single, possibly rotated, variant of a raw tile.
+///
+/// Nodes on the top and bottom sides are read from left to right, and nodes on the left and right
+/// sides are read from top to bottom, meaning two tiles fit together if their touching sides are equal.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Tile<const P: usize> {
+    /// Index of the raw tile that this tile was created from
+    index: usize,
+    /// The tile's rotation, relative to its raw tile
+    rotation: Rotation,
+    /// Layer that the tile is rendered on
+    layer: usize,
+    /// Relative likelihood of the tile being chosen
+    weight: i32,
+    /// The tile's nodes, indexed by side
+    nodes: [[Node; P]; 4],
+}
+
+impl<const P: usize> Tile<P> {
+    /// Creates a new unrotated tile from the provided raw tile, returning `None` if its nodes do
+    /// not match the tile precision
+    pub fn from_raw(index: usize, raw: &RawTile) -> Option<Self> {
+        let mut nodes = [[0; P]; 4];
+
+        for side in Side::Top {
+            nodes[usize::from(side)] = raw.nodes(side).as_slice().try_into().ok()?;
+        }
+
+        Some(Self {
+            index,
+            rotation: Rotation::D0,
+            layer: raw.layer,
+            weight: raw.weight,
+            nodes,
+        })
+    }
+
+    /// Returns the index of the raw tile that this tile was created from
+    pub const fn index(&self) -> usize {
+        self.index
+    }
+    /// Returns the tile's rotation
+    pub const fn rotation(&self) -> Rotation {
+        self.rotation
+    }
+    /// Returns the tile's layer
+    pub const fn layer(&self) -> usize {
+        self.layer
+    }
+    /// Returns the tile's weight
+    pub const fn weight(&self) -> i32 {
+        self.weight
+    }
+    /// Returns a reference to the nodes on the provided side
+    pub const fn nodes(&self, side: Side) -> &[Node; P] {
+        &self.nodes[side as usize]
+    }
+
+    /// Returns a copy of the tile rotated clockwise by a quarter turn
+    pub fn rotated_once(&self) -> Self {
+        self.turned(Rotation::D90)
+    }
+    /// Returns a copy of the tile rotated clockwise by the provided rotation
+    pub fn rotated(&self, rotation: Rotation) -> Self {
+        self.turned(rotation)
+    }
+
+    /// Returns a copy of the tile with its nodes mirrored from left to right.
+    ///
+    /// The mirrored tile keeps its index and rotation, so it only matches a variant of the same raw
+    /// tile if the raw tile is symmetric. Otherwise, its nodes may match a variant of another raw
+    /// tile drawn as its mirror image.
+    pub fn mirrored_x(&self) -> Self {
+        let [mut top, left, right, mut bottom] = self.nodes;
+
+        top.reverse();
+        bottom.reverse();
+
+        Self {
+            nodes: [top, right, left, bottom],
+            ..*self
+        }
+    }
+    /// Returns a copy of the tile with its nodes mirrored from top to bottom.
+    ///
+    /// The mirrored tile keeps its index and rotation, so it only matches a variant of the same raw
+    /// tile if the raw tile is symmetric. Otherwise, its nodes may match a variant of another raw
+    /// tile drawn as its mirror image.
+    pub fn mirrored_y(&self) -> Self {
+        let [top, mut left, mut right, bottom] = self.nodes;
+
+        left.reverse();
+        right.reverse();
+
+        Self {
+            nodes: [bottom, left, right, top],
+            ..*self
+        }
+    }
+
+    /// Returns `true` if the provided tile may be placed on the given side of this tile
+    pub fn fits(&self, other: &Self, side: Side) -> bool {
+        self.matches(side, other, side.opposite())
+    }
+    /// Returns `true` if both tiles have identical nodes
+    pub fn same_nodes(&self, other: &Self) -> bool {
+        Side::Top
+            .into_iter()
+            .all(|side| self.matches(side, other, side))
+    }
+}
+
+impl<const P: usize> Variant for Tile<P> {
+    type Nodes = [Node; P];
+
+    fn raw(&self) -> usize {
+        self.index
+    }
+    fn sides(&self) -> &[Self::Nodes; 4] {
+        &self.nodes
+    }
+    fn parts_mut(&mut self) -> (&mut Rotation, &mut i32, &mut [Self::Nodes; 4]) {
+        (&mut self.rotation, &mut self.weight, &mut self.nodes)
+    }
+}
+
+/// A tile variant with its nodes stored by side, letting tiles of compile time and runtime
+/// precision share how they are rotated, matched, and learned
+trait Variant: Clone {
+    /// Nodes along a single side
+    type Nodes: AsMut<[Node]> + PartialEq;
+
+    /// Returns the index of the raw tile that the variant was created from
+    fn raw(&self) -> usize;
+    /// Returns a reference to the variant's nodes, indexed by side
+    fn sides(&self) -> &[Self::Nodes; 4];
+    /// Returns mutable references to the variant's rotation, weight, and nodes
+    fn parts_mut(&mut self) -> (&mut Rotation, &mut i32, &mut [Self::Nodes; 4]);
+
+    /// Returns a copy of the variant rotated clockwise by the provided rotation
+    fn turned(&self, by: Rotation) -> Self {
+        let mut variant = self.clone();
+        let (rotation, _, nodes) = variant.parts_mut();
+
+        for _ in 0..usize::from(by) {
+            // Sides move from [top, left, right, bottom] to [left, bottom, top, right], where the
+            // sides that become the top and bottom are read in the opposite direction.
+            nodes.swap(0, 1);
+            nodes.swap(1, 3);
+            nodes.swap(2, 3);
+            nodes[usize::from(Side::Top)].as_mut().reverse();
+            nodes[usize::from(Side::Bottom)].as_mut().reverse();
+        }
+
+        *rotation = rotation
+            .into_iter()
+            .nth(usize::from(by))
+            .unwrap_or_default();
+        variant
+    }
+    /// Returns `true` if the nodes on the given side of this variant are the same as those on the
+    /// other side of the provided variant
+    fn matches(&self, side: Side, other: &Self, other_side: Side) -> bool {
+        self.sides()[usize::from(side)] == other.sides()[usize::from(other_side)]
+    }
+}
+
+/// Adds every distinct rotation of the provided variant to the list of variants
+fn push_rotations<T: Variant>(variants: &mut Vec<T>, variant: &T) {
+    for rotation in Rotation::D0 {
+        let rotated = variant.turned(rotation);
+
+        if !variants
+            .iter()
+            .any(|v| v.raw() == rotated.raw() && v.sides() == rotated.sides())
+        {
+            variants.push(rotated);
+        }
+    }
+}
+
+/// Sets the weight of each variant to the number of times it appears within the provided example
+/// map, returning the rules learned from the example
+fn learn<T: Variant>(variants: &mut [T], example: &VecGrid<usize>, wrap: bool) -> Adjacency {
+    let mut counts = vec![0_usize; variants.len()];
+
+    for index in example.indexes() {
+        if let Some(count) = example.get(index).and_then(|t| counts.get_mut(*t)) {
+            *count += 1;
+        }
+    }
+    for (variant, count) in variants.iter_mut().zip(counts) {
+        *variant.parts_mut().1 = i32::try_from(count).unwrap_or(i32::MAX);
+    }
+
+    Adjacency::from_example(variants.len(), example, wrap)
+}
+
+/// Stores every tile variant that may be used by a generator
+#[derive(Clone, Debug, Default, Hash, PartialEq, Eq)]
+pub struct TileSet<const P: usize> {
+    /// The tile set's identifier
+    id: usize,
+    /// The tile set's tile variants
+    tiles: Vec<Tile<P>>,
+    /// Number of raw tiles that have been added to the set
+    raws: usize,
+    /// Tiles that may be placed next to each other, if learned from an example map
+    learned: Option<Adjacency>,
+}
+
+impl<const P: usize> TileSet<P> {
+    /// Creates a new empty tile set
+    pub const fn new(id: usize) -> Self {
+        Self {
+            id,
+            tiles: vec![],
+            raws: 0,
+            learned: None,
+        }
+    }
+
+    /// Returns the tile set's identifier
+    pub const fn id(&self) -> usize {
+        self.id
+    }
+    /// Returns a reference to the tile set's tile variants
+    pub fn tiles(&self) -> &[Tile<P>] {
+        &self.tiles
+    }
+    /// Returns the total number of tile variants in the set
+    pub fn len(&self) -> usize {
+        self.tiles.len()
+    }
+    /// Returns `true` if the set contains no tile variants
+    pub fn is_empty(&self) -> bool {
+        self.tiles.is_empty()
+    }
+    /// Returns the tiles that may be placed next to each other, using the rules learned from an
+    /// example map if there are any, and otherwise matching the tiles' nodes
+    pub fn adjacency(&self) -> Adjacency {
+        self.learned
+            .clone()
+            .unwrap_or_else(|| Adjacency::from_nodes(&self.tiles))
+    }
+
+    /// Adds every distinct rotation of the provided raw tile to the set.
+    ///
+    /// Raw tiles whose nodes do not match the set's precision are skipped, returning `false`. Any
+    /// rules learned from an example map are discarded.
+    pub fn add_raw(&mut self, raw: &RawTile) -> bool {
+        let index = self.raws;
+        self.raws += 1;
+        self.learned = None;
+
+        let Some(tile) = Tile::from_raw(index, raw) else {
+            return false;
+        };
+
+        push_rotations(&mut self.tiles, &tile);
+        true
+    }
+    /// Adds every distinct rotation of each provided raw tile to the set
+    pub fn add_all_raws(&mut self, raws: &[RawTile]) {
+        for raw in raws {
+            self.add_raw(raw);
+        }
+    }
+    /// Learns which tiles may be placed next to each other from the provided example map, replacing
+    /// the rules created from the tiles' nodes.
+    ///
+    /// The example stores the index of the tile variant within each cell. Each tile's weight is set
+    /// to the number of times it appears within the example. If `wrap` is `true`, cells on opposite
+    /// edges of the example are treated as neighbours.
+    pub fn learn(&mut self, example: &VecGrid<usize>, wrap: bool) {
+        self.learned = Some(learn(&mut self.tiles, example, wrap));
+    }
+}
@@ -0,0 +1,451 @@
+use std::fmt::Display;
+
+use crate::collections::grid::vec::VecGrid;
+
+use super::{
+    super::{adjacency::Adjacency, Rotation, Side},
+    learn, push_rotations, Node, RawFile, RawTile, Tile, TileSet, Variant,
+};
+
+/// Defines `MAX_PRECISION` and the conversion used by `DynTileSet::dispatch` from the provided
+/// list of every precision from `1` upwards, so that the two always agree
+macro_rules! precisions {
+    ($($precision:literal),+) => {
+        /// Largest precision that tile sets can be converted to a compile time precision with
+        pub const MAX_PRECISION: usize = [$($precision),+].len();
+
+        /// Converts the provided tile set to its compile time precision and runs the given
+        /// operation on it
+        fn dispatch<O: WithPrecision>(set: &DynTileSet, operation: O) -> Result<O::Output, Error> {
+            Ok(match set.precision {
+                $($precision => operation.run(set.to_static::<$precision>()?),)+
+                precision => return Err(Error::Unsupported(precision)),
+            })
+        }
+    };
+}
+
+precisions!(1, 2, 3, 4, 5, 6, 7, 8);
+
+/// Error that may occur while converting a tile set to a compile time precision
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub enum Error {
+    /// The tile set's precision does not match the requested precision, stored as the set's
+    /// precision and the requested precision
+    WrongPrecision(usize, usize),
+    /// The tile set's precision is not between `1` and `MAX_PRECISION`
+    Unsupported(usize),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::WrongPrecision(precision, requested) => write!(
+                f,
+                "tile set has precision {precision}, but precision {requested} was requested"
+            ),
+            Self::Unsupported(precision) => write!(
+                f,
+                "precision {precision} is not supported, use a precision from 1 to {MAX_PRECISION}"
+            ),
+        }
+    }
+}
+
+/// Operation that runs on a tile set whose precision is known at compile time
+pub trait WithPrecision {
+    /// Value returned by the operation
+    type Output;
+
+    /// Runs the operation on the provided tile set
+    fn run<const P: usize>(self, set: TileSet<P>) -> Self::Output;
+}
+
+/// A single, possibly rotated, variant of a raw tile, whose precision is chosen at runtime.
+///
+/// Behaves the same as a `Tile`, which should be preferred wherever the precision is known.
+#[derive(Clone, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct DynTile {
+    /// Index of the raw tile that this tile was created from
+    index: usize,
+    /// The tile's rotation, relative to its raw tile
+    rotation: Rotation,
+    /// Layer that the tile is rendered on
+    layer: usize,
+    /// Relative likelihood of the tile being chosen
+    weight: i32,
+    /// The tile's nodes, indexed by side
+    nodes: [Vec<Node>; 4],
+}
+
+impl DynTile {
+    /// Creates a new unrotated tile from the provided raw tile, returning `None` if its nodes do
+    /// not match the given precision
+    pub fn from_raw(index: usize, raw: &RawTile, precision: usize) -> Option<Self> {
+        let nodes = Side::Top.into_iter().map(|side| raw.nodes(side).clone());
+        let nodes: [Vec<Node>; 4] = nodes.collect::<Vec<_>>().try_into().ok()?;
+
+        if nodes.iter().any(|n| n.len() != precision) {
+            return None;
+        }
+
+        Some(Self {
+            index,
+            rotation: Rotation::D0,
+            layer: raw.layer,
+            weight: raw.weight,
+            nodes,
+        })
+    }
+
+    /// Returns the index of the raw tile that this tile was created from
+    pub const fn index(&self) -> usize {
+        self.index
+    }
+    /// Returns the tile's rotation
+    pub const fn rotation(&self) -> Rotation {
+        self.rotation
+    }
+    /// Returns the tile's layer
+    pub const fn layer(&self) -> usize {
+        self.layer
+    }
+    /// Returns the tile's weight
+    pub const fn weight(&self) -> i32 {
+        self.weight
+    }
+    /// Returns the number of nodes on each side
+    pub fn precision(&self) -> usize {
+        self.nodes[0].len()
+    }
+    /// Returns a reference to the nodes on the provided side
+    pub fn nodes(&self, side: Side) -> &[Node] {
+        &self.nodes[usize::from(side)]
+    }
+
+    /// Returns a copy of the tile rotated clockwise by a quarter turn
+    pub fn rotated_once(&self) -> Self {
+        self.turned(Rotation::D90)
+    }
+    /// Returns a copy of the tile rotated clockwise by the provided rotation
+    pub fn rotated(&self, rotation: Rotation) -> Self {
+        self.turned(rotation)
+    }
+
+    /// Returns `true` if the provided tile may be placed on the given side of this tile
+    pub fn fits(&self, other: &Self, side: Side) -> bool {
+        self.matches(side, other, side.opposite())
+    }
+    /// Returns `true` if both tiles have identical nodes
+    pub fn same_nodes(&self, other: &Self) -> bool {
+        Side::Top
+            .into_iter()
+            .all(|side| self.matches(side, other, side))
+    }
+
+    /// Returns the tile with a compile time precision, or `None` if its precision is not `P`
+    pub fn to_static<const P: usize>(&self) -> Option<Tile<P>> {
+        let mut nodes = [[0; P]; 4];
+
+        for (side, nodes) in nodes.iter_mut().enumerate() {
+            *nodes = self.nodes[side].as_slice().try_into().ok()?;
+        }
+
+        Some(Tile {
+            index: self.index,
+            rotation: self.rotation,
+            layer: self.layer,
+            weight: self.weight,
+            nodes,
+        })
+    }
+}
+
+impl Variant for DynTile {
+    type Nodes = Vec<Node>;
+
+    fn raw(&self) -> usize {
+        self.index
+    }
+    fn sides(&self) -> &[Self::Nodes; 4] {
+        &self.nodes
+    }
+    fn parts_mut(&mut self) -> (&mut Rotation, &mut i32, &mut [Self::Nodes; 4]) {
+        (&mut self.rotation, &mut self.weight, &mut self.nodes)
+    }
+}
+
+impl<const P: usize> From<Tile<P>> for DynTile {
+    fn from(tile: Tile<P>) -> Self {
+        Self {
+            index: tile.index,
+            rotation: tile.rotation,
+            layer: tile.layer,
+            weight: tile.weight,
+            nodes: tile.nodes.map(|n| n.to_vec()),
+        }
+    }
+}
+
+/// Stores every tile variant that may be used by a generator, with a precision chosen at runtime.
+///
+/// Generators require a precision known at compile time, so sets are converted with `to_static`
+/// or `dispatch` before generating, where `dispatch` only supports precisions up to
+/// `MAX_PRECISION`.
+#[derive(Clone, Debug, Default, Hash, PartialEq, Eq)]
+pub struct DynTileSet {
+    /// The tile set's identifier
+    id: usize,
+    /// Number of nodes on each side of every tile
+    precision: usize,
+    /// The tile set's tile variants
+    tiles: Vec<DynTile>,
+    /// Number of raw tiles that have been added to the set
+    raws: usize,
+    /// Tiles that may be placed next to each other, if learned from an example map
+    learned: Option<Adjacency>,
+}
+
+impl DynTileSet {
+    /// Creates a new empty tile set with the given precision
+    pub const fn new(id: usize, precision: usize) -> Self {
+        Self {
+            id,
+            precision,
+            tiles: vec![],
+            raws: 0,
+            learned: None,
+        }
+    }
+    /// Creates a new tile set containing every tile of the provided file, using the file's
+    /// identifier and precision
+    pub fn from_file(file: &RawFile) -> Self {
+        let mut set = Self::new(file.id, file.precision);
+
+        set.add_all_raws(&file.tiles);
+        set
+    }
+
+    /// Returns the tile set's identifier
+    pub const fn id(&self) -> usize {
+        self.id
+    }
+    /// Returns the number of nodes on each side of every tile
+    pub const fn precision(&self) -> usize {
+        self.precision
+    }
+    /// Returns a reference to the tile set's tile variants
+    pub fn tiles(&self) -> &[DynTile] {
+        &self.tiles
+    }
+    /// Returns the total number of tile variants in the set
+    pub fn len(&self) -> usize {
+        self.tiles.len()
+    }
+    /// Returns `true` if the set contains no tile variants
+    pub fn is_empty(&self) -> bool {
+        self.tiles.is_empty()
+    }
+
+    /// Adds every distinct rotation of the provided raw tile to the set.
+    ///
+    /// Raw tiles whose nodes do not match the set's precision are skipped, returning `false`. Any
+    /// rules learned from an example map are discarded.
+    pub fn add_raw(&mut self, raw: &RawTile) -> bool {
+        let index = self.raws;
+        self.raws += 1;
+        self.learned = None;
+
+        let Some(tile) = DynTile::from_raw(index, raw, self.precision) else {
+            return false;
+        };
+
+        push_rotations(&mut self.tiles, &tile);
+        true
+    }
+    /// Adds every distinct rotation of each provided raw tile to the set
+    pub fn add_all_raws(&mut self, raws: &[RawTile]) {
+        for raw in raws {
+            self.add_raw(raw);
+        }
+    }
+    /// Learns which tiles may be placed next to each other from the provided example map, replacing
+    /// the rules created from the tiles' nodes.
+    ///
+    /// Behaves the same as `TileSet::learn`, which is kept when converting the set with
+    /// `to_static`.
+    pub fn learn(&mut self, example: &VecGrid<usize>, wrap: bool) {
+        self.learned = Some(learn(&mut self.tiles, example, wrap));
+    }
+
+    /// Returns the tile set with a compile time precision, failing if its precision is not `P`
+    pub fn to_static<const P: usize>(&self) -> Result<TileSet<P>, Error> {
+        let tiles = self
+            .tiles
+            .iter()
+            .map(DynTile::to_static)
+            .collect::<Option<_>>()
+            .filter(|_| self.precision == P)
+            .ok_or(Error::WrongPrecision(self.precision, P))?;
+
+        Ok(TileSet {
+            id: self.id,
+            tiles,
+            raws: self.raws,
+            learned: self.learned.clone(),
+        })
+    }
+    /// Converts the tile set to its compile time precision and runs the provided operation on it,
+    /// failing if the precision is not between `1` and `MAX_PRECISION`
+    pub fn dispatch<O: WithPrecision>(&self, operation: O) -> Result<O::Output, Error> {
+        dispatch(self, operation)
+    }
+}
+
+impl<const P: usize> From<&TileSet<P>> for DynTileSet {
+    fn from(set: &TileSet<P>) -> Self {
+        Self {
+            id: set.id,
+            precision: P,
+            tiles: set.tiles.iter().map(|t| DynTile::from(*t)).collect(),
+            raws: set.raws,
+            learned: set.learned.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{collections::grid::Grid, utility::generation::fixtures::raw};
+
+    /// Returns raw tiles of precision `2`, where the first has no symmetry
+    fn raws() -> Vec<RawTile> {
+        vec![
+            raw(&[1, 2], &[3, 4], &[5, 6], &[7, 8]),
+            raw(&[0, 0], &[0, 0], &[0, 0], &[0, 0]),
+        ]
+    }
+
+    /// Operation returning the precision and length of the tile set it runs on
+    struct Precision;
+
+    impl WithPrecision for Precision {
+        type Output = (usize, usize);
+
+        fn run<const P: usize>(self, set: TileSet<P>) -> Self::Output {
+            (P, set.len())
+        }
+    }
+
+    #[test]
+    fn tiles_behave_like_static_tiles() {
+        let raw = &raws()[0];
+        let tile = DynTile::from_raw(0, raw, 2).unwrap();
+        let fixed = Tile::<2>::from_raw(0, raw).unwrap();
+
+        for rotation in Rotation::D0 {
+            assert_eq!(
+                tile.rotated(rotation),
+                DynTile::from(fixed.rotated(rotation))
+            );
+            assert_eq!(
+                tile.rotated(rotation).to_static(),
+                Some(fixed.rotated(rotation))
+            );
+        }
+
+        let turned = tile.rotated_once();
+
+        assert_eq!(turned.rotation(), Rotation::D90);
+        assert_eq!(turned.nodes(Side::Top), [4, 3]);
+        assert!(tile
+            .rotated(Rotation::D270)
+            .rotated_once()
+            .same_nodes(&tile));
+        assert!(!turned.same_nodes(&tile));
+
+        let blank = DynTile::from_raw(1, &raws()[1], 2).unwrap();
+
+        assert!(blank.fits(&blank, Side::Top));
+        assert!(!tile.fits(&blank, Side::Top));
+        assert_eq!(tile.to_static::<3>(), None);
+    }
+
+    #[test]
+    fn raw_tiles_must_match_the_precision() {
+        let mut set = DynTileSet::new(0, 2);
+
+        assert_eq!(DynTile::from_raw(0, &raws()[0], 3), None);
+        assert!(!set.add_raw(&raw(&[0], &[0], &[0], &[0])));
+        assert!(set.add_raw(&raws()[1]));
+        assert_eq!(set.len(), 1);
+        assert_eq!(set.tiles()[0].index(), 1);
+    }
+
+    #[test]
+    fn sets_match_static_sets() {
+        let file = RawFile {
+            id: 3,
+            precision: 2,
+            tiles: raws(),
+            ..RawFile::default()
+        };
+        let set = DynTileSet::from_file(&file);
+        let mut fixed = TileSet::<2>::new(3);
+
+        fixed.add_all_raws(&file.tiles);
+
+        assert_eq!((set.id(), set.precision(), set.len()), (3, 2, 5));
+        assert_eq!(set.to_static::<2>(), Ok(fixed.clone()));
+        assert_eq!(DynTileSet::from(&fixed), set);
+        assert_eq!(set.to_static::<3>(), Err(Error::WrongPrecision(2, 3)));
+    }
+
+    #[test]
+    fn sets_are_dispatched_by_precision() {
+        let mut set = DynTileSet::new(0, 2);
+
+        set.add_all_raws(&raws());
+
+        assert_eq!(set.dispatch(Precision), Ok((2, 5)));
+        assert_eq!(
+            DynTileSet::new(0, MAX_PRECISION).dispatch(Precision),
+            Ok((MAX_PRECISION, 0))
+        );
+        assert_eq!(
+            DynTileSet::new(0, 0).dispatch(Precision),
+            Err(Error::Unsupported(0))
+        );
+        assert_eq!(
+            DynTileSet::new(0, MAX_PRECISION + 1).dispatch(Precision),
+            Err(Error::Unsupported(MAX_PRECISION + 1))
+        );
+    }
+
+    #[test]
+    fn learned_rules_are_kept_when_converting() {
+        let mut set = DynTileSet::new(0, 2);
+        let mut example = VecGrid::new(3, 1);
+
+        set.add_all_raws(&raws());
+        example.insert((0, 0), 4);
+        example.insert((1, 0), 4);
+        example.insert((2, 0), 0);
+        set.learn(&example, false);
+
+        let mut fixed = TileSet::<2>::new(0);
+
+        fixed.add_all_raws(&raws());
+        fixed.learn(&example, false);
+
+        assert_eq!(set.tiles()[4].weight(), 2);
+        assert_eq!(set.tiles()[1].weight(), 0);
+        assert_eq!(set.to_static::<2>(), Ok(fixed.clone()));
+        assert_eq!(fixed.adjacency().get(4, Side::Right), &[0, 4]);
+
+        set.add_raw(&raws()[1]);
+
+        assert_ne!(set.to_static::<2>().unwrap().adjacency(), fixed.adjacency());
+    }
+}
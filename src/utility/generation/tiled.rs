@@ -0,0 +1,1218 @@
+use std::{fmt::Display, fs, path::Path, str::FromStr};
+
+use bevy::utils::HashMap;
+use serde_json::{json, Value};
+use xml::{
+    reader::{EventReader, XmlEvent},
+    writer::{self, EmitterConfig, EventWriter},
+};
+
+use crate::collections::grid::{vec::VecGrid, Grid};
+
+use super::{
+    metadata::Property,
+    migration::CURRENT_VERSION,
+    tile::{Node, RawFile, RawTile, Tile},
+    Rotation,
+};
+
+/// Flag set on the global identifiers of tiles that are flipped horizontally
+const FLIPPED_HORIZONTALLY: u32 = 0x8000_0000;
+/// Flag set on the global identifiers of tiles that are flipped vertically
+const FLIPPED_VERTICALLY: u32 = 0x4000_0000;
+/// Flag set on the global identifiers of tiles that are flipped along their diagonal
+const FLIPPED_DIAGONALLY: u32 = 0x2000_0000;
+/// Every flag that may be set on global identifiers, including those only used by hexagonal maps
+const FLAGS: u32 = 0xF000_0000;
+
+/// Error that may occur while importing or exporting Tiled files
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub enum Error {
+    /// The file could not be read, stored as the reason
+    Io(String),
+    /// The file is not valid XML, stored as the reason
+    Xml(String),
+    /// The file is not valid JSON, stored as the reason
+    Json(String),
+    /// The file does not contain a `tileset` element at its root
+    NotATileset,
+    /// The file does not contain a map at its root
+    NotAMap,
+    /// The map's layers are stored using the given unsupported encoding or compression
+    UnsupportedEncoding(String),
+    /// The map could not be written, stored as the reason
+    Write(String),
+    /// The given element is missing the given attribute
+    MissingAttribute(String, String),
+    /// The given attribute contains an invalid value
+    InvalidAttribute(String, String),
+    /// The tileset does not contain a Wang set with the given name
+    UnknownWangSet(String),
+    /// The given precision is too low to represent the Wang set's colours, stored as the precision
+    /// and the lowest precision that can be used
+    Precision(usize, usize),
+    /// The raw tile at the given index does not have a Tiled tile identifier
+    NoTileId(usize),
+    /// The given global tile identifier does not refer to a known tile variant
+    UnknownGid(u32),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(reason) => write!(f, "could not read file: {reason}"),
+            Self::Xml(reason) => write!(f, "invalid XML: {reason}"),
+            Self::Json(reason) => write!(f, "invalid JSON: {reason}"),
+            Self::NotATileset => write!(f, "file does not contain a tileset"),
+            Self::NotAMap => write!(f, "file does not contain a map"),
+            Self::UnsupportedEncoding(encoding) => {
+                write!(f, "unsupported layer encoding {encoding:?}, use CSV instead")
+            }
+            Self::Write(reason) => write!(f, "could not write map: {reason}"),
+            Self::MissingAttribute(element, attribute) => {
+                write!(f, "<{element}> is missing attribute {attribute:?}")
+            }
+            Self::InvalidAttribute(attribute, value) => {
+                write!(f, "attribute {attribute:?} has invalid value {value:?}")
+            }
+            Self::UnknownWangSet(name) => write!(f, "no Wang set named {name:?}"),
+            Self::Precision(precision, required) => write!(
+                f,
+                "precision {precision} is too low for this Wang set, which requires at least {required}"
+            ),
+            Self::NoTileId(raw) => write!(f, "raw tile {raw} has no Tiled tile identifier"),
+            Self::UnknownGid(gid) => write!(f, "global tile identifier {gid} is not a known tile"),
+        }
+    }
+}
+
+/// Parts of each tile that a Wang set assigns colours to
+#[derive(Clone, Copy, Debug, Default, Hash, PartialEq, Eq)]
+pub enum WangKind {
+    /// Only corners are coloured
+    Corner,
+    /// Only edges are coloured
+    Edge,
+    /// Both corners and edges are coloured
+    #[default]
+    Mixed,
+}
+
+impl WangKind {
+    /// Returns the lowest precision that can represent every colour of a tile
+    pub const fn precision(self) -> usize {
+        match self {
+            Self::Corner => 2,
+            Self::Edge => 1,
+            Self::Mixed => 3,
+        }
+    }
+}
+
+/// Terrain colours assigned to the edges and corners of tiles
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct WangSet {
+    /// Name of the set
+    pub name: String,
+    /// Parts of each tile that are coloured
+    pub kind: WangKind,
+    /// Name of each colour, where colour `n` is stored at index `n - 1`
+    pub colors: Vec<String>,
+    /// Colours of each tile that belongs to the set, stored by tile identifier.
+    ///
+    /// Colours are stored clockwise starting from the top edge, alternating between edges and
+    /// corners, where `0` means no colour.
+    pub tiles: Vec<(usize, [Node; 8])>,
+}
+
+impl WangSet {
+    /// Returns the tile identifier of each raw tile created from the set, in the same order as
+    /// the tiles of `Tileset::to_raw_file`
+    pub fn ids(&self) -> Vec<usize> {
+        self.tiles.iter().map(|(id, _)| *id).collect()
+    }
+}
+
+/// A tile within a Tiled tileset
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TiledTile {
+    /// Identifier of the tile within the tileset
+    pub id: usize,
+    /// Path to the tile's own image, relative to the tileset, for image collection tilesets
+    pub image: Option<String>,
+    /// Relative likelihood of the tile being chosen
+    pub probability: f32,
+    /// Custom properties of the tile, stored as a name and a value
+    pub properties: Vec<(String, Property)>,
+}
+
+/// Tileset data, as authored in Tiled and stored in a `.tsx` file
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Tileset {
+    /// Name of the tileset
+    pub name: String,
+    /// Width and height of each tile in pixels
+    pub tile_size: (u32, u32),
+    /// Number of pixels between the tiles of the image
+    pub spacing: u32,
+    /// Number of pixels around the tiles of the image
+    pub margin: u32,
+    /// Number of tiles in each row of the image
+    pub columns: u32,
+    /// Path to the image containing every tile, relative to the tileset, unless each tile has its
+    /// own image
+    pub image: Option<String>,
+    /// Tiles that have their own image or properties
+    pub tiles: Vec<TiledTile>,
+    /// Every Wang set, known as terrain sets within Tiled
+    pub wang_sets: Vec<WangSet>,
+}
+
+impl Tileset {
+    /// Parses the provided `.tsx` file contents
+    pub fn parse(source: &str) -> Result<Self, Error> {
+        let root = Element::read(source)?;
+
+        if root.name != "tileset" {
+            return Err(Error::NotATileset);
+        }
+
+        let mut tileset = Self {
+            name: root.attribute("name").unwrap_or_default().to_string(),
+            tile_size: (root.parse("tilewidth")?, root.parse("tileheight")?),
+            spacing: root.parse_or("spacing", 0)?,
+            margin: root.parse_or("margin", 0)?,
+            columns: root.parse_or("columns", 0)?,
+            image: match root.child("image") {
+                Some(image) => Some(image.require("source")?.to_string()),
+                None => None,
+            },
+            ..Default::default()
+        };
+
+        for tile in root.children("tile") {
+            let image = match tile.child("image") {
+                Some(image) => Some(image.require("source")?.to_string()),
+                None => None,
+            };
+
+            tileset.tiles.push(TiledTile {
+                id: tile.parse("id")?,
+                image,
+                probability: tile.parse_or("probability", 1.0)?,
+                properties: properties(tile)?,
+            });
+        }
+
+        // Wang sets were stored directly within the tileset before they could be grouped.
+        let wang_sets = root
+            .children("wangsets")
+            .flat_map(|s| s.children("wangset"));
+
+        for set in wang_sets.chain(root.children("wangset")) {
+            let kind = match set.attribute("type") {
+                Some("corner") => WangKind::Corner,
+                Some("edge") => WangKind::Edge,
+                Some("mixed") | None => WangKind::Mixed,
+                Some(kind) => return Err(invalid("type", kind)),
+            };
+            let colors = set
+                .children("wangcolor")
+                .map(|c| c.attribute("name").unwrap_or_default().to_string())
+                .collect();
+            let tiles = set
+                .children("wangtile")
+                .map(|t| Ok((t.parse("tileid")?, wang_id(t.require("wangid")?)?)))
+                .collect::<Result<_, Error>>()?;
+
+            tileset.wang_sets.push(WangSet {
+                name: set.attribute("name").unwrap_or_default().to_string(),
+                kind,
+                colors,
+                tiles,
+            });
+        }
+
+        Ok(tileset)
+    }
+    /// Reads and parses the `.tsx` file at the provided path
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let source = fs::read_to_string(path).map_err(|e| Error::Io(e.to_string()))?;
+
+        Self::parse(&source)
+    }
+
+    /// Returns the Wang set with the given name, or the first Wang set if the name is empty
+    pub fn wang_set(&self, name: &str) -> Option<&WangSet> {
+        match name {
+            "" => self.wang_sets.first(),
+            _ => self.wang_sets.iter().find(|s| s.name == name),
+        }
+    }
+    /// Returns the tile with the given identifier, if it has its own image or properties
+    pub fn tile(&self, id: usize) -> Option<&TiledTile> {
+        self.tiles.iter().find(|t| t.id == id)
+    }
+    /// Returns the sprite source of the tile with the given identifier, where tiles cut from a
+    /// shared image use the region that they cover
+    pub fn source(&self, id: usize) -> String {
+        if let Some(image) = self.tile(id).and_then(|t| t.image.as_ref()) {
+            return image.clone();
+        }
+
+        let Some(image) = &self.image else {
+            return String::new();
+        };
+        let columns = self.columns.max(1);
+        let (width, height) = self.tile_size;
+        let (column, row) = (id as u32 % columns, id as u32 / columns);
+        let x = self.margin + column * (width + self.spacing);
+        let y = self.margin + row * (height + self.spacing);
+
+        format!("{image}@{x},{y},{width},{height}")
+    }
+
+    /// Converts every tile of the Wang set with the given name into a new tile file with the
+    /// provided identifier and precision, using the first Wang set if the name is empty.
+    ///
+    /// Each side's nodes start and end with the side's corner colours and are otherwise filled with
+    /// the edge's colour, where nodes are read in the same order as `Tile` nodes. Tiles are given
+    /// their `name`, `layer`, and `weight` properties if set, and are otherwise weighted by their
+    /// probability as a percentage. A `tags` property is split into tags at each comma, and every
+    /// other property is kept as a property of the raw tile.
+    pub fn to_raw_file(
+        &self,
+        wang_set: &str,
+        id: usize,
+        precision: usize,
+    ) -> Result<RawFile, Error> {
+        let set = self
+            .wang_set(wang_set)
+            .ok_or_else(|| Error::UnknownWangSet(wang_set.to_string()))?;
+
+        if precision < set.kind.precision() {
+            return Err(Error::Precision(precision, set.kind.precision()));
+        }
+
+        let mut file = RawFile {
+            id,
+            version: CURRENT_VERSION,
+            precision,
+            ..Default::default()
+        };
+
+        for &(tile, colors) in &set.tiles {
+            let [top, top_right, right, bottom_right, bottom, bottom_left, left, top_left] = colors;
+            let side = |start: Node, edge: Node, end: Node| -> Vec<Node> {
+                match set.kind {
+                    WangKind::Edge => vec![edge; precision],
+                    _ => (0..precision)
+                        .map(|i| match i {
+                            0 => start,
+                            i if i + 1 == precision => end,
+                            _ => edge,
+                        })
+                        .collect(),
+                }
+            };
+            let mut raw = RawTile {
+                source: self.source(tile),
+                weight: 100,
+                nodes: (
+                    side(top_left, top, top_right),
+                    side(top_left, left, bottom_left),
+                    side(top_right, right, bottom_right),
+                    side(bottom_left, bottom, bottom_right),
+                ),
+                ..Default::default()
+            };
+
+            if let Some(tiled) = self.tile(tile) {
+                raw.weight = (tiled.probability * 100.0).round() as i32;
+
+                for (name, value) in &tiled.properties {
+                    let text = value
+                        .as_str()
+                        .map_or_else(|| value.to_string(), str::to_string);
+
+                    match name.as_str() {
+                        "name" => raw.name = text,
+                        "layer" => raw.layer = int(name, value)?,
+                        "weight" => raw.weight = int(name, value)?,
+                        "tags" => {
+                            raw.tags = text
+                                .split(',')
+                                .map(|t| t.trim().to_string())
+                                .filter(|t| !t.is_empty())
+                                .collect()
+                        }
+                        _ => {
+                            raw.properties.insert(name.clone(), value.clone());
+                        }
+                    }
+                }
+            }
+
+            file.tiles.push(raw);
+        }
+
+        Ok(file)
+    }
+}
+
+/// Layer of tiles within a Tiled map
+#[derive(Clone, Debug, Default, Hash, PartialEq, Eq)]
+pub struct TiledLayer {
+    /// Name of the layer
+    pub name: String,
+    /// Global identifier of the tile within each cell in rows from top to bottom, where `0` means
+    /// an empty cell
+    pub data: Vec<u32>,
+}
+
+/// Map data, as edited in Tiled and stored in a `.tmx` or JSON file
+#[derive(Clone, Debug, Default, Hash, PartialEq, Eq)]
+pub struct TiledMap {
+    /// Number of cells horizontally
+    pub width: usize,
+    /// Number of cells vertically
+    pub height: usize,
+    /// Width and height of each tile in pixels
+    pub tile_size: (u32, u32),
+    /// Path to the tileset's `.tsx` file, relative to the map
+    pub tileset: String,
+    /// Global identifier of the tileset's first tile
+    pub first_gid: u32,
+    /// Every layer, from bottom to top
+    pub layers: Vec<TiledLayer>,
+}
+
+impl TiledMap {
+    /// Creates a new map from the provided generated map, referring to the given tileset.
+    ///
+    /// Each tile is placed on the layer matching its own, where `ids` are the Tiled tile
+    /// identifiers of each raw tile, such as those returned by `WangSet::ids`. Rotations are stored
+    /// as flipped tiles.
+    pub fn new<const P: usize>(
+        map: &VecGrid<Tile<P>>,
+        ids: &[usize],
+        tileset: &str,
+        tile_size: (u32, u32),
+    ) -> Result<Self, Error> {
+        let (width, height) = map.size();
+        let mut layers: Vec<usize> = map
+            .indexes()
+            .into_iter()
+            .filter_map(|index| map.get(index).map(|t| t.layer()))
+            .collect();
+
+        layers.sort_unstable();
+        layers.dedup();
+
+        let mut tiled = Self {
+            width,
+            height,
+            tile_size,
+            tileset: tileset.to_string(),
+            first_gid: 1,
+            layers: layers
+                .iter()
+                .map(|layer| TiledLayer {
+                    name: format!("Layer {layer}"),
+                    data: vec![0; width * height],
+                })
+                .collect(),
+        };
+
+        for (cell, index) in map.indexes().into_iter().enumerate() {
+            let Some(tile) = map.get(index) else {
+                continue;
+            };
+            let id = ids.get(tile.index()).ok_or(Error::NoTileId(tile.index()))?;
+            let layer = layers.binary_search(&tile.layer()).unwrap_or(0);
+
+            tiled.layers[layer].data[cell] = gid(tiled.first_gid, *id, tile.rotation());
+        }
+
+        Ok(tiled)
+    }
+    /// Parses the provided `.tmx` file contents, where layers must be stored as CSV
+    pub fn parse_tmx(source: &str) -> Result<Self, Error> {
+        let root = Element::read(source)?;
+
+        if root.name != "map" {
+            return Err(Error::NotAMap);
+        }
+
+        let tileset = root.child("tileset");
+        let mut map = Self {
+            width: root.parse("width")?,
+            height: root.parse("height")?,
+            tile_size: (root.parse("tilewidth")?, root.parse("tileheight")?),
+            tileset: match tileset {
+                Some(tileset) => tileset.require("source")?.to_string(),
+                None => String::new(),
+            },
+            first_gid: match tileset {
+                Some(tileset) => tileset.parse("firstgid")?,
+                None => 1,
+            },
+            layers: vec![],
+        };
+
+        for layer in root.children("layer") {
+            let data = layer
+                .child("data")
+                .ok_or_else(|| Error::MissingAttribute("layer".to_string(), "data".to_string()))?;
+
+            match (data.attribute("encoding"), data.attribute("compression")) {
+                (Some("csv"), None) => {}
+                (encoding, compression) => {
+                    let encoding = [encoding.unwrap_or("xml"), compression.unwrap_or_default()];
+
+                    return Err(Error::UnsupportedEncoding(
+                        encoding.join(" ").trim().to_string(),
+                    ));
+                }
+            }
+
+            map.layers.push(TiledLayer {
+                name: layer.attribute("name").unwrap_or_default().to_string(),
+                data: data
+                    .text
+                    .split(',')
+                    .map(|gid| parse("data", gid))
+                    .collect::<Result<_, _>>()?,
+            });
+        }
+
+        map.check()
+    }
+    /// Parses the provided JSON map file contents, where layers must be stored as arrays
+    pub fn parse_json(source: &str) -> Result<Self, Error> {
+        let root: Value = serde_json::from_str(source).map_err(|e| Error::Json(e.to_string()))?;
+
+        if root["type"] != "map" {
+            return Err(Error::NotAMap);
+        }
+
+        let number = |value: &Value, name: &str| {
+            value[name]
+                .as_u64()
+                .ok_or_else(|| invalid(name, &value[name].to_string()))
+        };
+        let tileset = &root["tilesets"][0];
+        let mut map = Self {
+            width: number(&root, "width")? as usize,
+            height: number(&root, "height")? as usize,
+            tile_size: (
+                number(&root, "tilewidth")? as u32,
+                number(&root, "tileheight")? as u32,
+            ),
+            tileset: tileset["source"].as_str().unwrap_or_default().to_string(),
+            first_gid: tileset["firstgid"].as_u64().unwrap_or(1) as u32,
+            layers: vec![],
+        };
+
+        for layer in root["layers"].as_array().into_iter().flatten() {
+            if layer["type"] != "tilelayer" {
+                continue;
+            }
+            if let Some(encoding) = layer["encoding"].as_str().filter(|e| *e != "csv") {
+                return Err(Error::UnsupportedEncoding(encoding.to_string()));
+            }
+
+            let data = layer["data"].as_array().into_iter().flatten();
+
+            map.layers.push(TiledLayer {
+                name: layer["name"].as_str().unwrap_or_default().to_string(),
+                data: data
+                    .map(|gid| {
+                        let value = gid.as_u64().and_then(|g| u32::try_from(g).ok());
+
+                        value.ok_or_else(|| invalid("data", &gid.to_string()))
+                    })
+                    .collect::<Result<_, _>>()?,
+            });
+        }
+
+        map.check()
+    }
+    /// Reads and parses the map file at the provided path, which is read as JSON if it has a
+    /// `.json` or `.tmj` extension, and as a `.tmx` file otherwise
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let source = fs::read_to_string(&path).map_err(|e| Error::Io(e.to_string()))?;
+
+        match path.as_ref().extension().and_then(|e| e.to_str()) {
+            Some("json" | "tmj") => Self::parse_json(&source),
+            _ => Self::parse_tmx(&source),
+        }
+    }
+
+    /// Returns the map as the contents of a `.tmx` file
+    pub fn to_tmx(&self) -> Result<String, Error> {
+        let mut bytes = vec![];
+        let mut writer = EmitterConfig::new()
+            .perform_indent(true)
+            .create_writer(&mut bytes);
+        let (width, height) = (self.width.to_string(), self.height.to_string());
+        let (tile_width, tile_height) =
+            (self.tile_size.0.to_string(), self.tile_size.1.to_string());
+        let next_layer = (self.layers.len() + 1).to_string();
+        let first_gid = self.first_gid.to_string();
+
+        write(
+            &mut writer,
+            writer::XmlEvent::start_element("map")
+                .attr("version", "1.10")
+                .attr("orientation", "orthogonal")
+                .attr("renderorder", "right-down")
+                .attr("width", &width)
+                .attr("height", &height)
+                .attr("tilewidth", &tile_width)
+                .attr("tileheight", &tile_height)
+                .attr("infinite", "0")
+                .attr("nextlayerid", &next_layer)
+                .attr("nextobjectid", "1"),
+        )?;
+        write(
+            &mut writer,
+            writer::XmlEvent::start_element("tileset")
+                .attr("firstgid", &first_gid)
+                .attr("source", &self.tileset),
+        )?;
+        write(&mut writer, writer::XmlEvent::end_element())?;
+
+        for (index, layer) in self.layers.iter().enumerate() {
+            let id = (index + 1).to_string();
+            let csv = self.rows(layer).join(",\n");
+
+            write(
+                &mut writer,
+                writer::XmlEvent::start_element("layer")
+                    .attr("id", &id)
+                    .attr("name", &layer.name)
+                    .attr("width", &width)
+                    .attr("height", &height),
+            )?;
+            write(
+                &mut writer,
+                writer::XmlEvent::start_element("data").attr("encoding", "csv"),
+            )?;
+            write(
+                &mut writer,
+                writer::XmlEvent::characters(&format!("\n{csv}\n")),
+            )?;
+            write(&mut writer, writer::XmlEvent::end_element())?;
+            write(&mut writer, writer::XmlEvent::end_element())?;
+        }
+
+        write(&mut writer, writer::XmlEvent::end_element())?;
+        String::from_utf8(bytes).map_err(|e| Error::Write(e.to_string()))
+    }
+    /// Returns the map as the contents of a JSON map file
+    pub fn to_json(&self) -> Result<String, Error> {
+        let layers: Vec<Value> = self
+            .layers
+            .iter()
+            .enumerate()
+            .map(|(index, layer)| {
+                json!({
+                    "type": "tilelayer",
+                    "id": index + 1,
+                    "name": layer.name,
+                    "x": 0,
+                    "y": 0,
+                    "width": self.width,
+                    "height": self.height,
+                    "opacity": 1,
+                    "visible": true,
+                    "data": layer.data,
+                })
+            })
+            .collect();
+        let map = json!({
+            "type": "map",
+            "version": "1.10",
+            "orientation": "orthogonal",
+            "renderorder": "right-down",
+            "width": self.width,
+            "height": self.height,
+            "tilewidth": self.tile_size.0,
+            "tileheight": self.tile_size.1,
+            "infinite": false,
+            "nextlayerid": self.layers.len() + 1,
+            "nextobjectid": 1,
+            "layers": layers,
+            "tilesets": [{ "firstgid": self.first_gid, "source": self.tileset }],
+        });
+
+        serde_json::to_string_pretty(&map).map_err(|e| Error::Write(e.to_string()))
+    }
+    /// Writes the map to the provided path, as JSON if it has a `.json` or `.tmj` extension, and
+    /// as a `.tmx` file otherwise
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), Error> {
+        let source = match path.as_ref().extension().and_then(|e| e.to_str()) {
+            Some("json" | "tmj") => self.to_json()?,
+            _ => self.to_tmx()?,
+        };
+
+        fs::write(path, source).map_err(|e| Error::Io(e.to_string()))
+    }
+
+    /// Returns the generated map that this map was created from, using the provided tiles, where
+    /// `ids` are the Tiled tile identifiers of each raw tile.
+    ///
+    /// Cells covered by several layers use the topmost layer's tile.
+    pub fn to_map<const P: usize>(
+        &self,
+        tiles: &[Tile<P>],
+        ids: &[usize],
+    ) -> Result<VecGrid<Tile<P>>, Error> {
+        let mut map = VecGrid::new(self.width, self.height);
+
+        for (cell, index) in map.indexes().into_iter().enumerate() {
+            let Some(gid) = self
+                .layers
+                .iter()
+                .rev()
+                .map(|l| l.data[cell])
+                .find(|g| *g != 0)
+            else {
+                continue;
+            };
+            let tile = cell_of(self.first_gid, gid)
+                .and_then(|(id, rotation)| {
+                    let raw = ids.iter().position(|i| *i == id)?;
+
+                    tiles
+                        .iter()
+                        .find(|t| t.index() == raw && t.rotation() == rotation)
+                })
+                .ok_or(Error::UnknownGid(gid))?;
+
+            map.insert(index, *tile);
+        }
+
+        Ok(map)
+    }
+
+    /// Returns the map if every layer covers the whole map
+    fn check(self) -> Result<Self, Error> {
+        let cells = self.width * self.height;
+
+        match self.layers.iter().find(|l| l.data.len() != cells) {
+            Some(layer) => Err(invalid("data", &format!("{} cells", layer.data.len()))),
+            None => Ok(self),
+        }
+    }
+    /// Returns each row of the provided layer as comma-separated global identifiers
+    fn rows(&self, layer: &TiledLayer) -> Vec<String> {
+        layer
+            .data
+            .chunks(self.width.max(1))
+            .map(|row| row.iter().map(u32::to_string).collect::<Vec<_>>().join(","))
+            .collect()
+    }
+}
+
+/// Element of an XML document, without its namespace
+#[derive(Clone, Debug, Default)]
+struct Element {
+    /// Name of the element
+    name: String,
+    /// Attributes of the element, stored by name
+    attributes: HashMap<String, String>,
+    /// Elements within this element, in order
+    children: Vec<Element>,
+    /// Text within this element
+    text: String,
+}
+
+impl Element {
+    /// Parses the provided XML document, returning its root element
+    fn read(source: &str) -> Result<Self, Error> {
+        let unbalanced = || Error::Xml("unbalanced elements".to_string());
+        let mut stack = vec![Self::default()];
+
+        for event in EventReader::from_str(source) {
+            match event.map_err(|e| Error::Xml(e.to_string()))? {
+                XmlEvent::StartElement {
+                    name, attributes, ..
+                } => stack.push(Self {
+                    name: name.local_name,
+                    attributes: attributes
+                        .into_iter()
+                        .map(|a| (a.name.local_name, a.value))
+                        .collect(),
+                    ..Default::default()
+                }),
+                XmlEvent::EndElement { .. } => {
+                    let element = stack.pop().ok_or_else(unbalanced)?;
+                    let parent = stack.last_mut().ok_or_else(unbalanced)?;
+
+                    parent.children.push(element);
+                }
+                XmlEvent::Characters(text) | XmlEvent::CData(text) => {
+                    if let Some(element) = stack.last_mut() {
+                        element.text.push_str(&text);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        stack
+            .pop()
+            .and_then(|document| document.children.into_iter().next())
+            .ok_or_else(|| Error::Xml("document has no root element".to_string()))
+    }
+
+    /// Returns the value of the attribute with the given name, if any
+    fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes.get(name).map(String::as_str)
+    }
+    /// Returns the value of the attribute with the given name
+    fn require(&self, name: &str) -> Result<&str, Error> {
+        self.attribute(name)
+            .ok_or_else(|| Error::MissingAttribute(self.name.clone(), name.to_string()))
+    }
+    /// Returns the parsed value of the attribute with the given name
+    fn parse<T: FromStr>(&self, name: &str) -> Result<T, Error> {
+        parse(name, self.require(name)?)
+    }
+    /// Returns the parsed value of the attribute with the given name, or the default if it is not
+    /// set
+    fn parse_or<T: FromStr>(&self, name: &str, default: T) -> Result<T, Error> {
+        self.attribute(name).map_or(Ok(default), |v| parse(name, v))
+    }
+    /// Returns the first child element with the given name, if any
+    fn child<'a>(&'a self, name: &'a str) -> Option<&'a Self> {
+        self.children(name).next()
+    }
+    /// Returns every child element with the given name
+    fn children<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Self> {
+        self.children.iter().filter(move |c| c.name == name)
+    }
+}
+
+/// Returns an error for the given attribute containing an invalid value
+fn invalid(attribute: &str, value: &str) -> Error {
+    Error::InvalidAttribute(attribute.to_string(), value.to_string())
+}
+
+/// Parses the provided value of the given attribute
+fn parse<T: FromStr>(attribute: &str, value: &str) -> Result<T, Error> {
+    value.trim().parse().map_err(|_| invalid(attribute, value))
+}
+
+/// Returns the provided property as an integer, where integers may also be stored as strings since
+/// Tiled does not check the values of string properties
+fn int<T: TryFrom<i64>>(name: &str, value: &Property) -> Result<T, Error> {
+    value
+        .as_int()
+        .or_else(|| value.as_str()?.trim().parse().ok())
+        .and_then(|v| T::try_from(v).ok())
+        .ok_or_else(|| invalid(name, &value.to_string()))
+}
+
+/// Returns the custom properties of the provided element, stored as a name and a value, where
+/// properties of types other than `bool`, `int`, and `float` are stored as strings
+fn properties(element: &Element) -> Result<Vec<(String, Property)>, Error> {
+    element
+        .children("properties")
+        .flat_map(|p| p.children("property"))
+        .map(|p| {
+            // Multi-line strings are stored as text instead of as an attribute.
+            let value = p.attribute("value").unwrap_or(&p.text);
+            let property = match p.attribute("type") {
+                Some("bool") => Property::Bool(parse("value", value)?),
+                Some("int") => Property::Int(parse("value", value)?),
+                Some("float") => Property::Float(parse("value", value)?),
+                _ => Property::String(value.to_string()),
+            };
+
+            Ok((p.require("name")?.to_string(), property))
+        })
+        .collect()
+}
+
+/// Parses the colours of a Wang tile, stored either as eight comma-separated colours or as up to
+/// eight hexadecimal digits in older files
+fn wang_id(value: &str) -> Result<[Node; 8], Error> {
+    let colors: Vec<Node> = match value.strip_prefix("0x").filter(|d| d.len() <= 8) {
+        // Leading zeroes are omitted, and the last digit is the top edge's colour.
+        Some(digits) => digits
+            .chars()
+            .rev()
+            .chain(std::iter::repeat('0'))
+            .take(8)
+            .map(|d| d.to_digit(16).map(|d| d as Node))
+            .collect::<Option<_>>()
+            .ok_or_else(|| invalid("wangid", value))?,
+        None => value
+            .split(',')
+            .map(|c| parse("wangid", c))
+            .collect::<Result<_, _>>()?,
+    };
+
+    colors.try_into().map_err(|_| invalid("wangid", value))
+}
+
+/// Writes the provided event to the XML writer
+fn write<'a>(
+    writer: &mut EventWriter<&mut Vec<u8>>,
+    event: impl Into<writer::XmlEvent<'a>>,
+) -> Result<(), Error> {
+    writer.write(event).map_err(|e| Error::Write(e.to_string()))
+}
+
+/// Returns the global identifier of the tile with the given identifier and rotation, where
+/// rotations are stored as flips applied along the diagonal first
+const fn gid(first_gid: u32, id: usize, rotation: Rotation) -> u32 {
+    let flags = match rotation {
+        Rotation::D0 => 0,
+        Rotation::D90 => FLIPPED_DIAGONALLY | FLIPPED_HORIZONTALLY,
+        Rotation::D180 => FLIPPED_HORIZONTALLY | FLIPPED_VERTICALLY,
+        Rotation::D270 => FLIPPED_DIAGONALLY | FLIPPED_VERTICALLY,
+    };
+
+    (first_gid + id as u32) | flags
+}
+
+/// Returns the tile identifier and rotation of the provided global identifier, or `None` if it
+/// belongs to another tileset or is mirrored
+const fn cell_of(first_gid: u32, gid: u32) -> Option<(usize, Rotation)> {
+    let rotation = match gid & (FLIPPED_HORIZONTALLY | FLIPPED_VERTICALLY | FLIPPED_DIAGONALLY) {
+        0 => Rotation::D0,
+        f if f == FLIPPED_DIAGONALLY | FLIPPED_HORIZONTALLY => Rotation::D90,
+        f if f == FLIPPED_HORIZONTALLY | FLIPPED_VERTICALLY => Rotation::D180,
+        f if f == FLIPPED_DIAGONALLY | FLIPPED_VERTICALLY => Rotation::D270,
+        _ => return None,
+    };
+
+    match (gid & !FLAGS).checked_sub(first_gid) {
+        Some(id) => Some((id as usize, rotation)),
+        None => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utility::generation::{
+        fixtures::{filled, rotations},
+        Side,
+    };
+
+    /// A tileset cut from a shared image, with a mixed Wang set and a legacy edge Wang set
+    const TILESET: &str = r##"<?xml version="1.0" encoding="UTF-8"?>
+<tileset version="1.10" name="terrain" tilewidth="16" tileheight="8" spacing="2" margin="1" columns="4">
+ <image source="terrain.png" width="74" height="38"/>
+ <tile id="1" probability="0.5">
+  <properties>
+   <property name="name" value="grass"/>
+   <property name="layer" type="int" value="2"/>
+   <property name="tags" value="soft, green,"/>
+   <property name="solid" type="bool" value="true"/>
+   <property name="notes">first line
+second line</property>
+  </properties>
+ </tile>
+ <tile id="2">
+  <properties>
+   <property name="weight" value="7"/>
+  </properties>
+ </tile>
+ <wangsets>
+  <wangset name="ground" type="mixed" tile="-1">
+   <wangcolor name="dirt" color="#ff0000" tile="-1" probability="1"/>
+   <wangcolor name="grass" color="#00ff00" tile="-1" probability="1"/>
+   <wangtile tileid="1" wangid="1,2,1,2,1,2,1,2"/>
+   <wangtile tileid="5" wangid="1,1,2,2,2,2,2,1"/>
+  </wangset>
+ </wangsets>
+ <wangset name="roads" type="edge">
+  <wangtile tileid="2" wangid="0x00010101"/>
+ </wangset>
+</tileset>"##;
+
+    #[test]
+    fn tilesets_are_parsed() {
+        let tileset = Tileset::parse(TILESET).unwrap();
+
+        assert_eq!(tileset.name, "terrain");
+        assert_eq!(tileset.tile_size, (16, 8));
+        assert_eq!(
+            (tileset.spacing, tileset.margin, tileset.columns),
+            (2, 1, 4)
+        );
+        assert_eq!(tileset.image.as_deref(), Some("terrain.png"));
+        assert_eq!(tileset.tile(1).unwrap().probability, 0.5);
+        assert_eq!(tileset.tile(2).unwrap().probability, 1.0);
+        assert_eq!(
+            tileset.tile(1).unwrap().properties[4],
+            (
+                "notes".to_string(),
+                Property::String("first line\nsecond line".to_string())
+            )
+        );
+        assert_eq!(tileset.wang_sets.len(), 2);
+        assert_eq!(tileset.wang_set("").unwrap().colors, ["dirt", "grass"]);
+        assert_eq!(tileset.wang_set("ground").unwrap().ids(), [1, 5]);
+        assert_eq!(tileset.wang_set("roads").unwrap().kind, WangKind::Edge);
+        assert_eq!(
+            tileset.wang_set("roads").unwrap().tiles,
+            [(2, [1, 0, 1, 0, 1, 0, 0, 0])]
+        );
+    }
+
+    #[test]
+    fn sources_cover_each_tile_of_the_image() {
+        let mut tileset = Tileset::parse(TILESET).unwrap();
+
+        assert_eq!(tileset.source(0), "terrain.png@1,1,16,8");
+        assert_eq!(tileset.source(5), "terrain.png@19,11,16,8");
+
+        tileset.image = None;
+        tileset.tiles[0].image = Some("grass.png".to_string());
+
+        assert_eq!(tileset.source(1), "grass.png");
+        assert_eq!(tileset.source(0), "");
+    }
+
+    #[test]
+    fn wang_tiles_become_raw_tiles() {
+        let file = Tileset::parse(TILESET)
+            .unwrap()
+            .to_raw_file("ground", 3, 4)
+            .unwrap();
+        let [grass, corner] = file.tiles.as_slice() else {
+            panic!("expected two tiles");
+        };
+
+        assert_eq!((file.id, file.precision), (3, 4));
+        assert_eq!(file.version, CURRENT_VERSION);
+        assert_eq!(*grass.nodes(Side::Top), [2, 1, 1, 2]);
+        assert_eq!(*corner.nodes(Side::Top), [1, 1, 1, 1]);
+        assert_eq!(*corner.nodes(Side::Left), [1, 2, 2, 2]);
+        assert_eq!(*corner.nodes(Side::Right), [1, 2, 2, 2]);
+        assert_eq!(*corner.nodes(Side::Bottom), [2, 2, 2, 2]);
+        assert_eq!(corner.source, "terrain.png@19,11,16,8");
+        assert_eq!(corner.weight, 100);
+    }
+
+    #[test]
+    fn tiled_properties_set_raw_tile_fields() {
+        let file = Tileset::parse(TILESET)
+            .unwrap()
+            .to_raw_file("", 0, 3)
+            .unwrap();
+        let grass = &file.tiles[0];
+
+        assert_eq!(grass.name, "grass");
+        assert_eq!((grass.layer, grass.weight), (2, 50));
+        assert_eq!(grass.tags, ["soft", "green"]);
+        assert_eq!(grass.properties.get("solid"), Some(&Property::Bool(true)));
+        assert_eq!(grass.properties.len(), 2);
+
+        let roads = Tileset::parse(TILESET)
+            .unwrap()
+            .to_raw_file("roads", 0, 2)
+            .unwrap();
+
+        assert_eq!(roads.tiles[0].weight, 7);
+        assert_eq!(*roads.tiles[0].nodes(Side::Left), [0, 0]);
+        assert_eq!(*roads.tiles[0].nodes(Side::Right), [1, 1]);
+    }
+
+    #[test]
+    fn wang_sets_must_exist_and_fit_the_precision() {
+        let tileset = Tileset::parse(TILESET).unwrap();
+
+        assert_eq!(
+            tileset.to_raw_file("water", 0, 3),
+            Err(Error::UnknownWangSet("water".to_string()))
+        );
+        assert_eq!(
+            tileset.to_raw_file("ground", 0, 2),
+            Err(Error::Precision(2, 3))
+        );
+    }
+
+    #[test]
+    fn invalid_tilesets_are_errors() {
+        assert_eq!(
+            Tileset::parse(r#"<map width="1"/>"#),
+            Err(Error::NotATileset)
+        );
+        assert_eq!(
+            Tileset::parse(r#"<tileset tilewidth="16"/>"#),
+            Err(Error::MissingAttribute(
+                "tileset".to_string(),
+                "tileheight".to_string()
+            ))
+        );
+        assert_eq!(
+            Tileset::parse(r#"<tileset tilewidth="16" tileheight="a"/>"#),
+            Err(Error::InvalidAttribute(
+                "tileheight".to_string(),
+                "a".to_string()
+            ))
+        );
+        assert_eq!(
+            Tileset::parse(&TILESET.replace("0x00010101", "1,2,3")),
+            Err(Error::InvalidAttribute(
+                "wangid".to_string(),
+                "1,2,3".to_string()
+            ))
+        );
+        assert_eq!(
+            Tileset::parse(&TILESET.replace("type=\"edge\"", "type=\"hex\"")),
+            Err(Error::InvalidAttribute(
+                "type".to_string(),
+                "hex".to_string()
+            ))
+        );
+        assert!(matches!(Tileset::parse("<tileset"), Err(Error::Xml(_))));
+    }
+
+    /// Returns every rotation of two asymmetric raw tiles on different layers, with Tiled tile
+    /// identifiers `4` and `9`
+    fn tiles() -> (Vec<Tile<1>>, Vec<usize>) {
+        let layered = |layer: usize, top: Node| RawTile {
+            layer,
+            ..filled::<1>([top, 0, 0, 0])
+        };
+
+        (rotations(&[layered(0, 1), layered(1, 2)]), vec![4, 9])
+    }
+
+    /// Returns a map using every rotation of the provided tiles, with an empty cell at the end
+    fn map(tiles: &[Tile<1>]) -> VecGrid<Tile<1>> {
+        let mut map = VecGrid::new(3, 3);
+
+        for (index, tile) in map.indexes().into_iter().zip(tiles) {
+            map.insert(index, *tile);
+        }
+
+        map
+    }
+
+    #[test]
+    fn rotations_are_stored_as_flips() {
+        let (tiles, ids) = tiles();
+        let tiled = TiledMap::new(&map(&tiles), &ids, "terrain.tsx", (16, 16)).unwrap();
+        let flags = |gid: u32| gid & FLAGS;
+
+        assert_eq!((tiled.width, tiled.height), (3, 3));
+        assert_eq!(tiled.layers.len(), 2);
+        assert!(tiled.layers[0].data[..4].iter().all(|g| g & !FLAGS == 5));
+        assert_eq!(
+            tiled.layers[0].data[..4]
+                .iter()
+                .map(|g| flags(*g))
+                .collect::<Vec<_>>(),
+            [
+                0,
+                FLIPPED_DIAGONALLY | FLIPPED_HORIZONTALLY,
+                FLIPPED_HORIZONTALLY | FLIPPED_VERTICALLY,
+                FLIPPED_DIAGONALLY | FLIPPED_VERTICALLY,
+            ]
+        );
+        assert_eq!(tiled.layers[1].data[4] & !FLAGS, 10);
+        assert_eq!(tiled.layers[0].data[4], 0);
+        assert_eq!(tiled.layers[1].data[8], 0);
+    }
+
+    #[test]
+    fn maps_round_trip_through_tmx_and_json() {
+        let (tiles, ids) = tiles();
+        let map = map(&tiles);
+        let tiled = TiledMap::new(&map, &ids, "terrain.tsx", (16, 8)).unwrap();
+
+        for parsed in [
+            TiledMap::parse_tmx(&tiled.to_tmx().unwrap()).unwrap(),
+            TiledMap::parse_json(&tiled.to_json().unwrap()).unwrap(),
+        ] {
+            assert_eq!(parsed, tiled);
+            assert_eq!(parsed.to_map(&tiles, &ids), Ok(map.clone()));
+        }
+    }
+
+    #[test]
+    fn maps_are_saved_and_loaded_by_extension() {
+        let (tiles, ids) = tiles();
+        let tiled = TiledMap::new(&map(&tiles), &ids, "terrain.tsx", (16, 16)).unwrap();
+
+        for extension in ["tmx", "json"] {
+            let path =
+                std::env::temp_dir().join(format!("tiled-{}.{extension}", std::process::id()));
+
+            tiled.save(&path).unwrap();
+
+            let source = fs::read_to_string(&path).unwrap();
+
+            assert_eq!(source.starts_with('{'), extension == "json");
+            assert_eq!(TiledMap::load(&path), Ok(tiled.clone()));
+
+            fs::remove_file(&path).unwrap();
+        }
+    }
+
+    #[test]
+    fn unknown_tiles_are_errors() {
+        let (tiles, ids) = tiles();
+        let map = map(&tiles);
+        let mut tiled = TiledMap::new(&map, &ids, "terrain.tsx", (16, 16)).unwrap();
+
+        assert_eq!(
+            TiledMap::new(&map, &ids[..1], "terrain.tsx", (16, 16)),
+            Err(Error::NoTileId(1))
+        );
+
+        // Mirrored tiles can not be represented by rotations.
+        tiled.layers[0].data[0] = 5 | FLIPPED_HORIZONTALLY;
+
+        assert_eq!(
+            tiled.to_map(&tiles, &ids),
+            Err(Error::UnknownGid(5 | FLIPPED_HORIZONTALLY))
+        );
+
+        tiled.layers[0].data[0] = 2;
+
+        assert_eq!(tiled.to_map(&tiles, &ids), Err(Error::UnknownGid(2)));
+    }
+
+    #[test]
+    fn invalid_maps_are_errors() {
+        let (tiles, ids) = tiles();
+        let tiled = TiledMap::new(&map(&tiles), &ids, "terrain.tsx", (16, 16)).unwrap();
+        let tmx = tiled.to_tmx().unwrap();
+
+        assert_eq!(
+            TiledMap::parse_tmx(&tmx.replace(
+                "encoding=\"csv\"",
+                "encoding=\"base64\" compression=\"zlib\""
+            )),
+            Err(Error::UnsupportedEncoding("base64 zlib".to_string()))
+        );
+        assert_eq!(
+            TiledMap::parse_tmx(&tmx.replace(
+                "width=\"3\" height=\"3\" tilewidth",
+                "width=\"4\" height=\"3\" tilewidth"
+            )),
+            Err(Error::InvalidAttribute(
+                "data".to_string(),
+                "9 cells".to_string()
+            ))
+        );
+        assert_eq!(TiledMap::parse_tmx(TILESET), Err(Error::NotAMap));
+        assert_eq!(
+            TiledMap::parse_json("{\"type\": \"tileset\"}"),
+            Err(Error::NotAMap)
+        );
+        assert!(matches!(TiledMap::parse_json("{"), Err(Error::Json(_))));
+        assert!(matches!(TiledMap::load("missing.tmx"), Err(Error::Io(_))));
+    }
+}
@@ -0,0 +1,419 @@
+use std::fmt::Display;
+
+use super::{
+    tile::{RawFile, Tile, TileSet},
+    Rotation, Side,
+};
+
+/// How serious a diagnostic is
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    /// The tile set can still be used, but may not behave as expected
+    Warning,
+    /// The tile set contains data that will cause generation to fail or ignore tiles
+    Error,
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Warning => write!(f, "warning"),
+            Self::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// A problem found within a tile file
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub enum Problem {
+    /// The file does not contain any tiles
+    Empty,
+    /// The file's precision does not match the precision it is being loaded with
+    WrongPrecision(usize, usize),
+    /// The nodes on the given side do not match the file's precision
+    WrongNodeCount(Side, usize, usize),
+    /// The tile has a weight of zero and will never be chosen over other tiles
+    ZeroWeight,
+    /// The tile has a negative weight
+    NegativeWeight(i32),
+    /// The tile's interior pattern is not the given number of nodes wide and tall, and will be
+    /// ignored
+    WrongInterior(usize),
+    /// The tile is identical to the tile at the given index, possibly after rotating
+    Duplicate(usize),
+    /// No tile, including rotations, fits on the given side of the tile
+    NoNeighbor(Side),
+    /// The tile can only ever be placed along the edge of a map, since every tile that fits next to
+    /// it eventually runs out of neighbours
+    Unplaceable,
+}
+
+impl Problem {
+    /// Returns the severity of the problem
+    pub const fn severity(&self) -> Severity {
+        match self {
+            Self::Empty
+            | Self::WrongPrecision(..)
+            | Self::WrongNodeCount(..)
+            | Self::NegativeWeight(_) => Severity::Error,
+            _ => Severity::Warning,
+        }
+    }
+}
+
+impl Display for Problem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Empty => write!(f, "file contains no tiles"),
+            Self::WrongPrecision(expected, found) => {
+                write!(f, "expected precision {expected}, found {found}")
+            }
+            Self::WrongNodeCount(side, expected, found) => {
+                write!(
+                    f,
+                    "expected {expected} nodes on {side:?} side, found {found}"
+                )
+            }
+            Self::ZeroWeight => write!(f, "weight is zero"),
+            Self::NegativeWeight(w) => write!(f, "weight {w} is negative"),
+            Self::WrongInterior(size) => write!(f, "interior is not {size} by {size} nodes"),
+            Self::Duplicate(i) => write!(f, "duplicate of tile {i}"),
+            Self::NoNeighbor(s) => write!(f, "no tile fits on {s:?} side"),
+            Self::Unplaceable => write!(f, "can never be placed away from the map's edge"),
+        }
+    }
+}
+
+/// A single problem, and the tile it was found in
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// Index of the raw tile that caused the problem, or `None` if it applies to the whole file
+    tile: Option<usize>,
+    /// The problem that was found
+    problem: Problem,
+}
+
+impl Diagnostic {
+    /// Creates a new diagnostic
+    pub const fn new(tile: Option<usize>, problem: Problem) -> Self {
+        Self { tile, problem }
+    }
+
+    /// Returns the index of the raw tile that caused the problem, if any
+    pub const fn tile(&self) -> Option<usize> {
+        self.tile
+    }
+    /// Returns the problem that was found
+    pub const fn problem(&self) -> Problem {
+        self.problem
+    }
+    /// Returns the severity of the problem
+    pub const fn severity(&self) -> Severity {
+        self.problem.severity()
+    }
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.tile {
+            Some(tile) => write!(f, "{}: tile {tile}: {}", self.severity(), self.problem),
+            None => write!(f, "{}: {}", self.severity(), self.problem),
+        }
+    }
+}
+
+/// Every problem found while validating a tile file
+#[derive(Clone, Debug, Default, Hash, PartialEq, Eq)]
+pub struct Report(Vec<Diagnostic>);
+
+impl Report {
+    /// Validates the provided file, assuming it will be loaded into a tile set of precision `P`
+    pub fn new<const P: usize>(file: &RawFile) -> Self {
+        let mut report = Self::default();
+
+        if file.tiles.is_empty() {
+            report.push(None, Problem::Empty);
+        }
+        if file.precision != P {
+            report.push(None, Problem::WrongPrecision(P, file.precision));
+        }
+
+        for (index, raw) in file.tiles.iter().enumerate() {
+            for side in Side::Top {
+                let count = raw.nodes(side).len();
+
+                if count != file.precision {
+                    report.push(
+                        Some(index),
+                        Problem::WrongNodeCount(side, file.precision, count),
+                    );
+                }
+            }
+
+            match raw.weight {
+                0 => report.push(Some(index), Problem::ZeroWeight),
+                w if w < 0 => report.push(Some(index), Problem::NegativeWeight(w)),
+                _ => {}
+            }
+
+            let size = file.precision.saturating_sub(2);
+
+            if !raw.interior.is_empty()
+                && (raw.interior.len() != size || raw.interior.iter().any(|r| r.len() != size))
+            {
+                report.push(Some(index), Problem::WrongInterior(size));
+            }
+        }
+
+        let mut set = TileSet::<P>::new(file.id);
+        set.add_all_raws(&file.tiles);
+
+        report.check_duplicates(file, &set);
+        report.check_neighbors(&set);
+        report.sort();
+        report
+    }
+
+    /// Adds a diagnostic to the report
+    pub fn push(&mut self, tile: Option<usize>, problem: Problem) {
+        self.0.push(Diagnostic::new(tile, problem));
+    }
+    /// Returns a reference to the report's diagnostics
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        &self.0
+    }
+    /// Returns the diagnostics that apply to the raw tile at the provided index
+    pub fn for_tile(&self, tile: usize) -> impl Iterator<Item = &Diagnostic> {
+        self.0.iter().filter(move |d| d.tile == Some(tile))
+    }
+    /// Returns the total number of diagnostics in the report
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+    /// Returns `true` if the report contains no diagnostics
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+    /// Returns `true` if the report contains any errors
+    pub fn has_errors(&self) -> bool {
+        self.0.iter().any(|d| d.severity() == Severity::Error)
+    }
+
+    /// Sorts diagnostics by tile, placing file-wide diagnostics first
+    fn sort(&mut self) {
+        self.0.sort_by_key(|d| d.tile.map_or(0, |t| t + 1));
+    }
+    /// Reports every tile that matches an earlier tile, possibly after rotating
+    fn check_duplicates<const P: usize>(&mut self, file: &RawFile, set: &TileSet<P>) {
+        let base: Vec<&Tile<P>> = set
+            .tiles()
+            .iter()
+            .filter(|t| t.rotation() == Rotation::D0)
+            .collect();
+
+        for (i, tile) in base.iter().enumerate() {
+            let raw = &file.tiles[tile.index()];
+            let original = base[..i].iter().find(|other| {
+                let other_raw = &file.tiles[other.index()];
+
+                other_raw.source == raw.source
+                    && other_raw.layer == raw.layer
+                    && Rotation::D0
+                        .into_iter()
+                        .any(|r| other.rotated(r).same_nodes(tile))
+            });
+
+            if let Some(original) = original {
+                self.push(Some(tile.index()), Problem::Duplicate(original.index()));
+            }
+        }
+    }
+    /// Reports tiles that are missing neighbours, and tiles that can never be placed
+    fn check_neighbors<const P: usize>(&mut self, set: &TileSet<P>) {
+        let tiles = set.tiles();
+
+        for tile in tiles.iter().filter(|t| t.rotation() == Rotation::D0) {
+            for side in Side::Top {
+                if !tiles.iter().any(|other| tile.fits(other, side)) {
+                    self.push(Some(tile.index()), Problem::NoNeighbor(side));
+                }
+            }
+        }
+
+        // Repeatedly remove tiles that are missing a neighbour among the remaining tiles, leaving
+        // only those that can be surrounded on every side.
+        let mut alive = vec![true; tiles.len()];
+        let mut changed = true;
+
+        while changed {
+            changed = false;
+
+            for (i, tile) in tiles.iter().enumerate() {
+                let supported = Side::Top
+                    .into_iter()
+                    .all(|side| (0..tiles.len()).any(|j| alive[j] && tile.fits(&tiles[j], side)));
+
+                if alive[i] && !supported {
+                    alive[i] = false;
+                    changed = true;
+                }
+            }
+        }
+
+        let mut unplaceable: Vec<usize> = tiles.iter().map(Tile::index).collect();
+
+        unplaceable.sort_unstable();
+        unplaceable.dedup();
+        unplaceable.retain(|index| {
+            self.for_tile(*index)
+                .all(|d| !matches!(d.problem, Problem::NoNeighbor(_)))
+                && !tiles
+                    .iter()
+                    .zip(&alive)
+                    .any(|(tile, alive)| *alive && tile.index() == *index)
+        });
+
+        for index in unplaceable {
+            self.push(Some(index), Problem::Unplaceable);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utility::generation::{fixtures::raw, tile::RawTile};
+
+    /// Returns a file of precision 3 containing the given tiles
+    fn file(tiles: Vec<RawTile>) -> RawFile {
+        RawFile {
+            precision: 3,
+            tiles,
+            ..RawFile::default()
+        }
+    }
+
+    #[test]
+    fn valid_file_has_no_diagnostics() {
+        let file = file(vec![raw(&[0; 3], &[0; 3], &[0; 3], &[0; 3])]);
+
+        assert!(Report::new::<3>(&file).is_empty());
+    }
+
+    #[test]
+    fn empty_file_is_an_error() {
+        let report = Report::new::<3>(&file(vec![]));
+
+        assert!(report.has_errors());
+        assert_eq!(
+            report.diagnostics()[0],
+            Diagnostic::new(None, Problem::Empty)
+        );
+    }
+
+    #[test]
+    fn wrong_precision_is_an_error() {
+        let report = Report::new::<2>(&file(vec![raw(&[0; 3], &[0; 3], &[0; 3], &[0; 3])]));
+
+        assert!(report.has_errors());
+        assert!(report
+            .diagnostics()
+            .contains(&Diagnostic::new(None, Problem::WrongPrecision(2, 3))));
+    }
+
+    #[test]
+    fn wrong_node_count_is_reported_per_side() {
+        let report = Report::new::<3>(&file(vec![raw(&[0; 3], &[0; 2], &[0; 3], &[0; 3])]));
+        let problems: Vec<Problem> = report.for_tile(0).map(Diagnostic::problem).collect();
+
+        assert_eq!(problems, [Problem::WrongNodeCount(Side::Left, 3, 2)]);
+        assert!(report.has_errors());
+    }
+
+    #[test]
+    fn weights_are_checked() {
+        let mut zero = raw(&[0; 3], &[0; 3], &[0; 3], &[0; 3]);
+        let mut negative = raw(&[1; 3], &[1; 3], &[1; 3], &[1; 3]);
+
+        zero.weight = 0;
+        negative.weight = -2;
+
+        let report = Report::new::<3>(&file(vec![zero, negative]));
+
+        assert!(report
+            .for_tile(0)
+            .any(|d| d.problem() == Problem::ZeroWeight));
+        assert!(report
+            .for_tile(1)
+            .any(|d| d.problem() == Problem::NegativeWeight(-2)));
+        assert_eq!(Problem::ZeroWeight.severity(), Severity::Warning);
+        assert!(report.has_errors());
+    }
+
+    #[test]
+    fn wrong_interior_is_a_warning() {
+        let mut tile = raw(&[0; 3], &[0; 3], &[0; 3], &[0; 3]);
+
+        tile.interior = vec![vec![0, 0]];
+
+        let report = Report::new::<3>(&file(vec![tile]));
+
+        assert_eq!(
+            report.diagnostics(),
+            [Diagnostic::new(Some(0), Problem::WrongInterior(1))]
+        );
+        assert!(!report.has_errors());
+    }
+
+    #[test]
+    fn rotated_duplicates_are_reported() {
+        let corner = raw(&[1; 3], &[1; 3], &[0; 3], &[0; 3]);
+        let rotated = raw(&[1; 3], &[0; 3], &[1; 3], &[0; 3]);
+        let report = Report::new::<3>(&file(vec![corner, rotated]));
+
+        assert!(report
+            .for_tile(1)
+            .any(|d| d.problem() == Problem::Duplicate(0)));
+    }
+
+    #[test]
+    fn missing_neighbors_are_reported() {
+        let report = Report::new::<3>(&file(vec![raw(&[0; 3], &[0; 3], &[0; 3], &[1, 2, 3])]));
+
+        assert!(report
+            .for_tile(0)
+            .any(|d| d.problem() == Problem::NoNeighbor(Side::Bottom)));
+    }
+
+    #[test]
+    fn tiles_only_fitting_dead_ends_are_unplaceable() {
+        // The dead end fits nothing on its top, and is the only tile that fits on the stub's top.
+        let blank = raw(&[0; 3], &[0; 3], &[0; 3], &[0; 3]);
+        let dead_end = raw(&[1, 2, 3], &[0; 3], &[0; 3], &[4, 5, 6]);
+        let stub = raw(&[4, 5, 6], &[0; 3], &[0; 3], &[0; 3]);
+        let report = Report::new::<3>(&file(vec![blank, dead_end, stub]));
+
+        assert!(report
+            .for_tile(1)
+            .any(|d| d.problem() == Problem::NoNeighbor(Side::Top)));
+        assert_eq!(
+            report.for_tile(2).collect::<Vec<_>>(),
+            [&Diagnostic::new(Some(2), Problem::Unplaceable)]
+        );
+        assert!(report.for_tile(0).next().is_none());
+    }
+
+    #[test]
+    fn diagnostics_display_their_tile_and_severity() {
+        let diagnostic = Diagnostic::new(Some(4), Problem::NegativeWeight(-1));
+
+        assert_eq!(
+            diagnostic.to_string(),
+            "error: tile 4: weight -1 is negative"
+        );
+        assert_eq!(
+            Diagnostic::new(None, Problem::Empty).to_string(),
+            "error: file contains no tiles"
+        );
+    }
+}
@@ -0,0 +1,893 @@
+use std::fmt::Display;
+
+use rand::{distributions::WeightedIndex, prelude::Distribution, rngs::StdRng, Rng, SeedableRng};
+
+use crate::collections::grid::{vec::VecGrid, Grid, Idx};
+
+use super::{
+    adjacency::Adjacency,
+    constraint::{Constraint, Window},
+    explanation::Explanation,
+    frequency::{Frequency, Tally},
+    graph::Graph,
+    tile::Tile,
+    Side,
+};
+
+/// Error that may occur while running a generator
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub enum Error {
+    /// The generator was not provided any tiles
+    NoTiles,
+    /// The cell at the given index ran out of possible tiles while propagating
+    Contradiction(Idx),
+    /// A cell ran out of possible tiles while generating, explaining how each tile was removed
+    Exhausted(Box<Explanation>),
+    /// The fixed tile at the given index is not one of the generator's tiles
+    UnknownTile(Idx),
+    /// The cell at the given index is outside of the map
+    OutOfBounds(Idx),
+    /// The fixed tile at the given index does not fit next to the fixed tile on the given side
+    Mismatch(Idx, Side),
+    /// No path can be formed between the given indexes
+    NoPath(Idx, Idx),
+    /// No detail tiles were provided for the region tile created from the given raw tile
+    MissingRegion(usize),
+    /// The given raw tile was used too many or too few times, stored as the raw tile, the number of
+    /// cells using it, and its target number of cells
+    OffTarget(usize, usize, usize),
+    /// No tile is a mirror image of the variant of the given raw tile, so it can never be placed
+    /// within a symmetric map
+    NoMirror(usize),
+    /// A constraint that is only supported on grid maps was used by a graph generator
+    GridOnly,
+    /// The cell of the region map at the given index has no region tile
+    EmptyRegion(Idx),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NoTiles => write!(f, "no tiles were provided"),
+            Self::Contradiction(index) => write!(f, "cell {index:?} ran out of possible tiles"),
+            Self::Exhausted(explanation) => write!(f, "{explanation}"),
+            Self::UnknownTile(index) => write!(f, "fixed tile at {index:?} is not a known tile"),
+            Self::OutOfBounds(index) => write!(f, "cell {index:?} is outside of the map"),
+            Self::Mismatch(index, side) => {
+                write!(
+                    f,
+                    "fixed tile at {index:?} does not fit its {side:?} neighbour"
+                )
+            }
+            Self::NoPath(start, end) => write!(f, "no path exists from {start:?} to {end:?}"),
+            Self::MissingRegion(region) => write!(f, "no detail tiles for region tile {region}"),
+            Self::OffTarget(raw, count, target) => {
+                write!(f, "raw tile {raw} used {count} times, expected {target}")
+            }
+            Self::NoMirror(raw) => write!(f, "raw tile {raw} has no mirrored variant"),
+            Self::GridOnly => write!(f, "constraint is only supported on grid maps"),
+            Self::EmptyRegion(index) => write!(f, "region cell {index:?} has no tile"),
+        }
+    }
+}
+
+/// Reason that a tile was removed from a cell
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub enum Cause {
+    /// The cell was collapsed to the given tile
+    Collapsed(usize),
+    /// The cell was pinned to the given tile
+    Pinned(usize),
+    /// No remaining tile of the cell at the given index fit on the given side of the removed tile
+    Neighbor(Idx, Side),
+    /// The tile was removed by a constraint
+    Constraint,
+}
+
+/// A single tile being removed from a cell
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub struct Ban {
+    /// Index of the cell that the tile was removed from
+    pub index: Idx,
+    /// The removed tile
+    pub tile: usize,
+    /// Reason that the tile was removed
+    pub cause: Cause,
+}
+
+/// Tracks which tiles are still possible for every cell of a generator's output
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub struct Wave {
+    /// Size of the wave
+    size: Idx,
+    /// Step at which each tile was banned for each cell, or `usize::MAX` if the tile is still possible
+    cells: Vec<Vec<usize>>,
+    /// Number of remaining possible tiles for each cell
+    counts: Vec<usize>,
+    /// Every ban made so far, in order, where each ban's position is its step
+    history: Vec<Ban>,
+    /// Whether the wave's cells are laid out as a grid, rather than taken from a graph
+    grid: bool,
+}
+
+impl Wave {
+    /// Creates a new wave where every tile is possible in every cell
+    pub fn new(size: Idx, tiles: usize) -> Self {
+        let capacity = size.0 * size.1;
+
+        Self {
+            size,
+            cells: vec![vec![usize::MAX; tiles]; capacity],
+            counts: vec![tiles; capacity],
+            history: vec![],
+            grid: true,
+        }
+    }
+
+    /// Returns the size of the wave
+    pub const fn size(&self) -> Idx {
+        self.size
+    }
+    /// Returns `true` if the wave's cells are laid out as a grid, rather than taken from a graph
+    pub const fn is_grid(&self) -> bool {
+        self.grid
+    }
+    /// Returns `true` if the provided index is within the wave
+    pub const fn contains(&self, (x, y): Idx) -> bool {
+        x < self.size.0 && y < self.size.1
+    }
+    /// Returns the flat position of the provided index
+    const fn position(&self, (x, y): Idx) -> usize {
+        y * self.size.0 + x
+    }
+
+    /// Returns `true` if the given tile is still possible at the provided index
+    pub fn is_possible(&self, index: Idx, tile: usize) -> bool {
+        self.cells[self.position(index)][tile] == usize::MAX
+    }
+    /// Returns the number of tiles still possible at the provided index
+    pub fn count(&self, index: Idx) -> usize {
+        self.counts[self.position(index)]
+    }
+    /// Returns an iterator over every tile still possible at the provided index
+    pub fn possible(&self, index: Idx) -> impl Iterator<Item = usize> + '_ {
+        self.possible_before(index, usize::MAX)
+    }
+    /// Returns an iterator over every tile that was possible at the provided index before the given
+    /// step
+    pub fn possible_before(&self, index: Idx, step: usize) -> impl Iterator<Item = usize> + '_ {
+        self.cells[self.position(index)]
+            .iter()
+            .enumerate()
+            .filter_map(move |(t, s)| (*s >= step).then_some(t))
+    }
+    /// Returns the step at which the given tile was banned at the provided index, if it was banned
+    pub fn banned_at(&self, index: Idx, tile: usize) -> Option<usize> {
+        let step = self.cells[self.position(index)][tile];
+
+        (step != usize::MAX).then_some(step)
+    }
+    /// Returns every ban made so far, in order
+    pub fn history(&self) -> &[Ban] {
+        &self.history
+    }
+
+    /// Marks the given tile as impossible at the provided index because of a constraint, returning
+    /// `true` if it was possible
+    pub fn ban(&mut self, index: Idx, tile: usize) -> bool {
+        self.ban_because(index, tile, Cause::Constraint)
+    }
+    /// Marks the given tile as impossible at the provided index for the given reason, returning
+    /// `true` if it was possible
+    pub fn ban_because(&mut self, index: Idx, tile: usize, cause: Cause) -> bool {
+        let position = self.position(index);
+        let cell = &mut self.cells[position][tile];
+
+        if *cell == usize::MAX {
+            *cell = self.history.len();
+            self.counts[position] -= 1;
+            self.history.push(Ban { index, tile, cause });
+            true
+        } else {
+            false
+        }
+    }
+    /// Makes every tile that was banned at or after the given step possible again, removing those
+    /// bans from the history
+    pub fn undo(&mut self, step: usize) {
+        let undone = self.history.split_off(step.min(self.history.len()));
+
+        for ban in undone {
+            let position = self.position(ban.index);
+
+            self.cells[position][ban.tile] = usize::MAX;
+            self.counts[position] += 1;
+        }
+    }
+    /// Bans every tile other than the given tile at the provided index
+    pub fn collapse(&mut self, index: Idx, tile: usize) {
+        self.collapse_because(index, tile, Cause::Collapsed(tile));
+    }
+    /// Bans every tile other than the given tile at the provided index for the given reason
+    pub fn collapse_because(&mut self, index: Idx, tile: usize, cause: Cause) {
+        let others: Vec<usize> = self.possible(index).filter(|t| *t != tile).collect();
+
+        for other in others {
+            self.ban_because(index, other, cause);
+        }
+    }
+}
+
+/// Wave function collapse map generator
+#[derive(Debug)]
+pub struct Generator<const P: usize> {
+    /// Size of the generated map
+    size: Idx,
+    /// Tiles that may be placed within the map
+    tiles: Vec<Tile<P>>,
+    /// Tiles that fit on each side of each tile
+    rules: Adjacency,
+    /// Cells that are fixed to a tile before generating, stored as an index and a tile
+    pins: Vec<(Idx, usize)>,
+    /// Additional rules that are enforced after each propagation
+    constraints: Vec<Box<dyn Constraint<P>>>,
+    /// Cells and connections to generate on instead of a grid, if any
+    graph: Option<Graph>,
+    /// Target share of the map for each raw tile, if tile weights should be adjusted to match it
+    frequency: Option<Frequency>,
+    /// Random number generator used to collapse cells
+    rng: StdRng,
+}
+
+impl<const P: usize> Generator<P> {
+    /// Creates a new generator for a map of the given size using the provided tiles, where tiles may
+    /// be placed next to each other if their nodes fit
+    pub fn new(width: usize, height: usize, tiles: &[Tile<P>]) -> Self {
+        Self::with_adjacency(width, height, tiles, Adjacency::from_nodes(tiles))
+    }
+    /// Creates a new generator for a map of the given size using the provided tiles, where tiles may
+    /// only be placed next to each other if allowed by the given rules
+    pub fn with_adjacency(
+        width: usize,
+        height: usize,
+        tiles: &[Tile<P>],
+        rules: Adjacency,
+    ) -> Self {
+        Self {
+            size: (width, height),
+            tiles: tiles.to_vec(),
+            rules,
+            pins: vec![],
+            constraints: vec![],
+            graph: None,
+            frequency: None,
+            rng: StdRng::from_entropy(),
+        }
+    }
+    /// Creates a new generator for the cells of the provided graph using the given tiles.
+    ///
+    /// Generated maps are one cell tall, with each cell of the graph stored at the index `(cell, 0)`.
+    /// Since the graph decides which cells are connected, `wrap` has no effect on these maps.
+    pub fn from_graph(graph: Graph, tiles: &[Tile<P>]) -> Self {
+        let mut generator = Self::new(graph.len(), 1, tiles);
+
+        generator.graph = Some(graph);
+        generator
+    }
+    /// Creates a new generator for the window of the given size at the provided offset within this
+    /// generator's map, keeping the pins within the window and each constraint that can be enforced
+    /// within it.
+    ///
+    /// If `wrap` is `true`, cells on opposite edges of the whole map are neighbours. Windows are
+    /// always found within a grid, so graph generators are not supported.
+    pub fn window(&self, offset: Idx, size: Idx, wrap: bool) -> Self {
+        let window = Window {
+            offset,
+            size,
+            map: self.size,
+            wrap,
+        };
+        let mut generator = Self::with_adjacency(size.0, size.1, &self.tiles, self.rules.clone());
+
+        generator.pins = self
+            .pins
+            .iter()
+            .filter_map(|(index, tile)| Some((window.local(*index)?, *tile)))
+            .collect();
+        generator.constraints = self
+            .constraints
+            .iter()
+            .filter_map(|c| c.window(&window))
+            .collect();
+        generator
+    }
+    /// Sets the seed of the generator's random number generator
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.rng = StdRng::seed_from_u64(seed);
+        self
+    }
+
+    /// Fixes the cell at the provided index to the given tile.
+    ///
+    /// Pins are checked when the generator runs, failing if the cell is outside of the map or the
+    /// tile is not one of the generator's tiles.
+    pub fn pin(&mut self, index: Idx, tile: usize) {
+        self.pins.retain(|(i, _)| *i != index);
+        self.pins.push((index, tile));
+    }
+    /// Removes every pinned cell
+    pub fn clear_pins(&mut self) {
+        self.pins.clear();
+    }
+    /// Adjusts tile weights while generating so that each raw tile covers its target share of the
+    /// map.
+    ///
+    /// Generation stops as soon as a raw tile covers more of the map than its tolerance allows, and
+    /// starts again until the frequency's attempts run out, failing if any share still ends up
+    /// outside of the target's tolerance.
+    pub fn match_frequency(&mut self, frequency: Frequency) {
+        self.frequency = Some(frequency);
+    }
+    /// Adds a constraint that is enforced while generating
+    pub fn constrain<C: Constraint<P> + 'static>(&mut self, constraint: C) {
+        self.constraints.push(Box::new(constraint));
+    }
+
+    /// Returns the size of the generated map
+    pub const fn size(&self) -> Idx {
+        self.size
+    }
+    /// Returns a reference to the generator's tiles
+    pub fn tiles(&self) -> &[Tile<P>] {
+        &self.tiles
+    }
+    /// Returns the indexes of the tiles that fit on the given side of the provided tile
+    pub fn rules(&self, tile: usize, side: Side) -> &[usize] {
+        self.rules.get(tile, side)
+    }
+    /// Returns a reference to the graph being generated on, if any
+    pub const fn graph(&self) -> Option<&Graph> {
+        self.graph.as_ref()
+    }
+    /// Returns the cells next to the provided index within a map of the given size, and the side
+    /// they are next to
+    pub fn neighbors(&self, index: Idx, size: Idx, wrap: bool) -> Vec<(Side, Idx)> {
+        match &self.graph {
+            Some(graph) => graph
+                .neighbors(index.0)
+                .iter()
+                .map(|(side, cell)| (*side, (*cell, 0)))
+                .collect(),
+            None => Side::Top
+                .into_iter()
+                .filter_map(|side| Some((side, side.neighbor(index, size, wrap)?)))
+                .collect(),
+        }
+    }
+    /// Returns a reference to the generator's pinned cells
+    pub fn pins(&self) -> &[(Idx, usize)] {
+        &self.pins
+    }
+    /// Returns a reference to the generator's constraints
+    pub fn constraints(&self) -> &[Box<dyn Constraint<P>>] {
+        &self.constraints
+    }
+
+    /// Runs the generator, returning the generated map.
+    ///
+    /// If `wrap` is `true`, cells on opposite edges of the map are treated as neighbours.
+    pub fn run(&mut self, wrap: bool) -> Result<VecGrid<Tile<P>>, Error> {
+        let pins = self.pins.clone();
+
+        self.generate(self.size, &pins, wrap)
+    }
+    /// Fills every empty cell of the provided map, keeping every filled cell as it is.
+    ///
+    /// The returned map is the same size as the provided map, and the generator's own pinned cells
+    /// are only used where the provided map is empty.
+    pub fn inpaint(
+        &mut self,
+        map: &VecGrid<Tile<P>>,
+        wrap: bool,
+    ) -> Result<VecGrid<Tile<P>>, Error> {
+        let size = map.size();
+        let mut fixed = VecGrid::new(size.0, size.1);
+
+        for index in map.indexes() {
+            if let Some(tile) = map.get(index) {
+                let tile = self.tiles.iter().position(|t| t == tile);
+
+                fixed.insert(index, tile.ok_or(Error::UnknownTile(index))?);
+            }
+        }
+
+        let mut pins = vec![];
+
+        // Filled cells are checked against the generator's rules, which may have been learned
+        // rather than created from the tiles' nodes.
+        for index in fixed.indexes() {
+            let Some(&tile) = fixed.get(index) else {
+                continue;
+            };
+
+            for (side, neighbor) in self.neighbors(index, size, wrap) {
+                if fixed
+                    .get(neighbor)
+                    .is_some_and(|n| !self.rules.get(tile, side).contains(n))
+                {
+                    return Err(Error::Mismatch(index, side));
+                }
+            }
+
+            pins.push((index, tile));
+        }
+
+        for (index, tile) in &self.pins {
+            if map.contains_index(*index) && map.get(*index).is_none() {
+                pins.push((*index, *tile));
+            }
+        }
+
+        self.generate(size, &pins, wrap)
+    }
+    /// Generates a map of the given size with the provided cells pinned
+    fn generate(
+        &mut self,
+        size: Idx,
+        pins: &[(Idx, usize)],
+        wrap: bool,
+    ) -> Result<VecGrid<Tile<P>>, Error> {
+        if self.tiles.is_empty() {
+            return Err(Error::NoTiles);
+        }
+
+        let attempts = self.frequency.as_ref().map_or(1, Frequency::attempts);
+        let mut attempt = 1;
+
+        loop {
+            let mut wave = Wave::new(size, self.tiles.len());
+            let result = self.fill(&mut wave, pins, wrap).and_then(|()| {
+                let map = self.resolve(&wave);
+
+                match &self.frequency {
+                    Some(frequency) => frequency.check(&map).map(|()| map),
+                    None => Ok(map),
+                }
+            });
+
+            return match result {
+                Err(Error::OffTarget(..)) if attempt < attempts => {
+                    attempt += 1;
+                    continue;
+                }
+                Err(Error::Contradiction(index)) => {
+                    let explanation = Explanation::new(&self.rules, &wave, index);
+
+                    Err(Error::Exhausted(Box::new(explanation)))
+                }
+                result => result,
+            };
+        }
+    }
+    /// Collapses every cell of the provided wave, stopping early once a raw tile covers more of
+    /// the map than its target frequency allows.
+    ///
+    /// Choices that close off a path constraint's every route are undone, so generation only fails
+    /// with `Error::NoPath` once every choice has been tried.
+    fn fill(&mut self, wave: &mut Wave, pins: &[(Idx, usize)], wrap: bool) -> Result<(), Error> {
+        self.restrict(wave, pins, wrap)?;
+
+        let (width, height) = wave.size();
+        let mut tally = Tally::new(&self.tiles, wave);
+        // Each choice is stored as the step before the cell was collapsed, the cell, and its tile.
+        let mut choices = vec![];
+
+        while let Some(index) = self.observe(wave) {
+            let tile = self.choose(wave, &tally, index);
+
+            choices.push((wave.history().len(), index, tile));
+            wave.collapse(index, tile);
+
+            match self.propagate(wave, vec![index], wrap) {
+                Err(error @ Error::NoPath(..)) => {
+                    self.backtrack(wave, &mut choices, wrap, error)?;
+                    tally = Tally::new(&self.tiles, wave);
+                }
+                result => result?,
+            }
+
+            if let Some(frequency) = &self.frequency {
+                tally.update(&self.tiles, wave);
+                frequency.check_tally(&tally, width * height)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Undoes the latest choice and removes its tile from its cell instead, undoing earlier choices
+    /// in turn until the wave propagates without failing. Returns the provided error once every
+    /// choice has been undone.
+    fn backtrack(
+        &self,
+        wave: &mut Wave,
+        choices: &mut Vec<(usize, Idx, usize)>,
+        wrap: bool,
+        error: Error,
+    ) -> Result<(), Error> {
+        while let Some((step, index, tile)) = choices.pop() {
+            wave.undo(step);
+            wave.ban(index, tile);
+
+            if wave.count(index) > 0 && self.propagate(wave, vec![index], wrap).is_ok() {
+                return Ok(());
+            }
+        }
+
+        Err(error)
+    }
+
+    /// Creates a new wave of the given size with the provided cells pinned, removing any tiles that
+    /// no longer fit next to them
+    pub fn prepare(&self, size: Idx, pins: &[(Idx, usize)], wrap: bool) -> Result<Wave, Error> {
+        let mut wave = Wave::new(size, self.tiles.len());
+
+        self.restrict(&mut wave, pins, wrap)?;
+        Ok(wave)
+    }
+    /// Pins the provided cells within the given wave and initializes each constraint, removing any
+    /// tiles that no longer fit
+    fn restrict(&self, wave: &mut Wave, pins: &[(Idx, usize)], wrap: bool) -> Result<(), Error> {
+        wave.grid = self.graph.is_none();
+
+        for (index, tile) in pins {
+            if !wave.contains(*index) {
+                return Err(Error::OutOfBounds(*index));
+            }
+            if *tile >= self.tiles.len() {
+                return Err(Error::UnknownTile(*index));
+            }
+            if !wave.is_possible(*index, *tile) {
+                return Err(Error::Contradiction(*index));
+            }
+
+            wave.collapse_because(*index, *tile, Cause::Pinned(*tile));
+        }
+
+        // Every cell is checked against its neighbours, since cells that start with a single
+        // possible tile are never collapsed.
+        let (width, height) = wave.size();
+        let mut stack: Vec<Idx> = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .collect();
+
+        for constraint in &self.constraints {
+            stack.extend(constraint.initialize(&self.tiles, wave, wrap)?);
+        }
+
+        self.propagate(wave, stack, wrap)
+    }
+    /// Removes tiles that no longer fit from the neighbours of each changed cell, then enforces each
+    /// constraint until no more tiles are removed
+    pub fn propagate(&self, wave: &mut Wave, mut stack: Vec<Idx>, wrap: bool) -> Result<(), Error> {
+        loop {
+            let changed = self.propagate_rules(wave, stack, wrap)?;
+
+            stack = vec![];
+
+            for constraint in &self.constraints {
+                stack.extend(constraint.enforce(&self.tiles, wave, &changed, wrap)?);
+            }
+
+            if stack.is_empty() {
+                return Ok(());
+            }
+        }
+    }
+    /// Removes tiles that no longer fit from the neighbours of each changed cell, returning every
+    /// cell that was changed, including the provided cells
+    fn propagate_rules(
+        &self,
+        wave: &mut Wave,
+        mut stack: Vec<Idx>,
+        wrap: bool,
+    ) -> Result<Vec<Idx>, Error> {
+        let mut changed = stack.clone();
+
+        while let Some(index) = stack.pop() {
+            for (side, neighbor) in self.neighbors(index, wave.size(), wrap) {
+                let mut allowed = vec![false; self.tiles.len()];
+
+                for tile in wave.possible(index) {
+                    for other in self.rules.get(tile, side) {
+                        allowed[*other] = true;
+                    }
+                }
+
+                let banned: Vec<usize> = wave.possible(neighbor).filter(|t| !allowed[*t]).collect();
+
+                if banned.is_empty() {
+                    continue;
+                }
+                for tile in banned {
+                    wave.ban_because(neighbor, tile, Cause::Neighbor(index, side.opposite()));
+                }
+                if wave.count(neighbor) == 0 {
+                    return Err(Error::Contradiction(neighbor));
+                }
+
+                stack.push(neighbor);
+                changed.push(neighbor);
+            }
+        }
+
+        changed.sort_unstable();
+        changed.dedup();
+        Ok(changed)
+    }
+
+    /// Returns the index of an uncollapsed cell with the fewest possible tiles
+    fn observe(&mut self, wave: &Wave) -> Option<Idx> {
+        let (width, height) = wave.size();
+        let mut lowest = usize::MAX;
+        let mut candidates = vec![];
+
+        for y in 0..height {
+            for x in 0..width {
+                let count = wave.count((x, y));
+
+                if count <= 1 || count > lowest {
+                    continue;
+                }
+                if count < lowest {
+                    lowest = count;
+                    candidates.clear();
+                }
+
+                candidates.push((x, y));
+            }
+        }
+
+        (!candidates.is_empty()).then(|| candidates[self.rng.gen_range(0..candidates.len())])
+    }
+    /// Returns a random tile that is possible at the provided index.
+    ///
+    /// Tiles are weighted by how far their raw tile is below its target frequency, if any. Once
+    /// every possible raw tile has met its target, or without a target, tiles are weighted by their
+    /// own weight instead. Tiles are only equally likely when every possible tile has a weight of
+    /// `0`.
+    fn choose(&mut self, wave: &Wave, tally: &Tally, index: Idx) -> usize {
+        let possible: Vec<usize> = wave.possible(index).collect();
+        let mut weights = match &self.frequency {
+            Some(frequency) => self.deficits(frequency, wave, tally, &possible),
+            None => vec![],
+        };
+
+        if weights.iter().all(|w| *w <= 0.0) {
+            weights = possible
+                .iter()
+                .map(|t| f64::from(self.tiles[*t].weight().max(0)))
+                .collect();
+        }
+        if weights.iter().all(|w| *w <= 0.0) {
+            weights = vec![1.0; possible.len()];
+        }
+
+        match WeightedIndex::new(weights) {
+            Ok(dist) => possible[dist.sample(&mut self.rng)],
+            Err(_) => possible[0],
+        }
+    }
+    /// Returns the weight of each of the provided tiles, based on how far each tile's raw tile is
+    /// below its target number of cells, split evenly between the possible tiles sharing a raw tile
+    fn deficits(
+        &self,
+        frequency: &Frequency,
+        wave: &Wave,
+        tally: &Tally,
+        possible: &[usize],
+    ) -> Vec<f64> {
+        let (width, height) = wave.size();
+        let raws = self.tiles.iter().map(|t| t.index() + 1).max().unwrap_or(0);
+        let mut variants = vec![0_u32; raws];
+
+        for tile in possible {
+            variants[self.tiles[*tile].index()] += 1;
+        }
+
+        possible
+            .iter()
+            .map(|t| {
+                let raw = self.tiles[*t].index();
+                let target = frequency.target(raw, width * height);
+                let deficit = target.saturating_sub(tally.count(raw)) as f64;
+
+                deficit / f64::from(variants[raw])
+            })
+            .collect()
+    }
+    /// Converts a fully collapsed wave into a map of tiles
+    fn resolve(&self, wave: &Wave) -> VecGrid<Tile<P>> {
+        let (width, height) = wave.size();
+        let mut map = VecGrid::new(width, height);
+
+        for index in map.indexes() {
+            if let Some(tile) = wave.possible(index).next() {
+                map.insert(index, self.tiles[tile]);
+            }
+        }
+
+        map
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utility::generation::{
+        fixtures::{filled, tile},
+        tile::RawTile,
+    };
+
+    #[test]
+    fn generated_tiles_fit_their_neighbors() {
+        let tiles = [
+            tile(0, [0; 4]),
+            tile(1, [0, 0, 1, 0]),
+            tile(2, [0, 1, 0, 0]),
+        ];
+        let map = Generator::new(6, 4, &tiles)
+            .with_seed(7)
+            .run(false)
+            .unwrap();
+
+        for index in map.indexes() {
+            let tile = map.get(index).unwrap();
+
+            for side in Side::Top {
+                if let Some(neighbor) = side.neighbor(index, map.size(), false) {
+                    assert!(tile.fits(map.get(neighbor).unwrap(), side));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn single_tiles_are_checked_against_their_neighbors() {
+        let mut generator = Generator::new(2, 1, &[tile(0, [0, 0, 1, 0])]);
+
+        assert!(matches!(generator.run(false), Err(Error::Exhausted(_))));
+    }
+
+    #[test]
+    fn pinned_cells_keep_their_tile() {
+        let tiles = [tile(0, [0; 4]), tile(1, [1; 4])];
+        let mut generator = Generator::new(3, 3, &tiles).with_seed(1);
+
+        generator.pin((1, 1), 1);
+
+        let map = generator.run(false).unwrap();
+
+        assert!(map
+            .indexes()
+            .into_iter()
+            .all(|i| map.get(i) == Some(&tiles[1])));
+    }
+
+    #[test]
+    fn undone_bans_are_possible_again() {
+        let mut wave = Wave::new((2, 1), 3);
+
+        wave.ban((0, 0), 2);
+
+        let step = wave.history().len();
+
+        wave.collapse((1, 0), 0);
+        wave.undo(step);
+
+        assert_eq!(wave.count((1, 0)), 3);
+        assert!(!wave.is_possible((0, 0), 2));
+        assert_eq!(wave.history().len(), 1);
+    }
+
+    #[test]
+    fn invalid_pins_are_errors() {
+        let mut generator = Generator::new(3, 3, &[tile(0, [0; 4])]);
+
+        generator.pin((20, 0), 0);
+        assert_eq!(generator.run(false), Err(Error::OutOfBounds((20, 0))));
+
+        generator.clear_pins();
+        generator.pin((0, 0), 4);
+        assert_eq!(generator.run(false), Err(Error::UnknownTile((0, 0))));
+    }
+
+    #[test]
+    fn inpainting_keeps_filled_cells() {
+        let tiles = [
+            tile(0, [0; 4]),
+            tile(1, [0, 0, 1, 0]),
+            tile(2, [0, 1, 0, 0]),
+        ];
+        let mut map = VecGrid::new(3, 1);
+
+        map.insert((0, 0), tiles[1]);
+
+        let filled = Generator::new(3, 1, &tiles)
+            .with_seed(3)
+            .inpaint(&map, false)
+            .unwrap();
+
+        assert_eq!(filled.get((0, 0)), Some(&tiles[1]));
+        assert_eq!(filled.get((1, 0)), Some(&tiles[2]));
+        assert!(filled.get((2, 0)).is_some());
+    }
+
+    #[test]
+    fn inpainting_rejects_invalid_cells() {
+        let tiles = [tile(0, [0; 4]), tile(1, [1; 4])];
+        let mut generator = Generator::new(2, 1, &tiles);
+        let mut map = VecGrid::new(2, 1);
+
+        map.insert((0, 0), tiles[0]);
+        map.insert((1, 0), tiles[1]);
+        assert_eq!(
+            generator.inpaint(&map, false),
+            Err(Error::Mismatch((0, 0), Side::Right))
+        );
+
+        map.remove((1, 0));
+        map.insert((0, 0), tile(5, [0; 4]));
+        assert_eq!(
+            generator.inpaint(&map, false),
+            Err(Error::UnknownTile((0, 0)))
+        );
+    }
+
+    #[test]
+    fn empty_tiles_are_an_error() {
+        let mut generator = Generator::<1>::new(2, 2, &[]);
+
+        assert_eq!(generator.run(false), Err(Error::NoTiles));
+    }
+
+    #[test]
+    fn tiles_without_weight_are_equally_likely() {
+        let raw = RawTile {
+            weight: 0,
+            ..filled::<1>([0; 4])
+        };
+        let tiles = [0, 1].map(|i| Tile::<1>::from_raw(i, &raw).unwrap());
+        let map = Generator::new(8, 8, &tiles)
+            .with_seed(0)
+            .run(false)
+            .unwrap();
+
+        for index in 0..2 {
+            assert!(map.iter().flatten().any(|t| t.index() == index));
+        }
+    }
+
+    #[test]
+    fn inpainting_follows_the_generator_rules() {
+        let tiles = [tile(0, [0; 4]), tile(1, [1; 4])];
+        let mut rules = Adjacency::from_nodes(&tiles);
+        let mut map = VecGrid::new(2, 1);
+
+        // The rules allow tiles whose nodes do not fit, and forbid tiles whose nodes do.
+        rules.link(0, Side::Right, 1);
+        map.insert((0, 0), tiles[0]);
+        map.insert((1, 0), tiles[1]);
+
+        let mut generator = Generator::with_adjacency(2, 1, &tiles, rules);
+
+        assert_eq!(generator.inpaint(&map, false), Ok(map.clone()));
+
+        map.insert((1, 0), tiles[0]);
+
+        assert_eq!(
+            generator.inpaint(&map, false),
+            Err(Error::Mismatch((0, 0), Side::Right))
+        );
+    }
+}